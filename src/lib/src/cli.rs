@@ -0,0 +1,522 @@
+use crate::meta::{compare, SortKey, DATE_FORMAT};
+use crate::todo::{
+    file_path, note_at, validate_file_name, visible_notes, FileList, Note, NoteEnum,
+};
+use crate::ui::app::{App, EditorMode};
+use crate::ui::register::Destination;
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// A file of the data directory as used by `export` and `import`
+#[derive(Serialize, Deserialize)]
+pub struct FileData {
+    pub name: String,
+    /// Lines preceding the first heading
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preamble: Vec<String>,
+    pub lists: FileList,
+}
+
+/// Split a `file` or `file/list` target into the file index and list title
+fn split_target<'a>(app: &App, target: &'a str) -> Result<(usize, Option<&'a str>), String> {
+    // Match file names first so that list titles may contain slashes
+    let file_index = app
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| target == *file || target.starts_with(&format!("{}/", file)))
+        .max_by_key(|(_, file)| file.len())
+        .map(|(i, _)| i)
+        .ok_or_else(|| format!("No such file: {}", target))?;
+
+    match target[app.files[file_index].len()..].strip_prefix('/') {
+        Some(title) if !title.is_empty() => Ok((file_index, Some(title))),
+        _ => Ok((file_index, None)),
+    }
+}
+
+/// Resolve a `file` or `file/list` target to file and list indices
+pub fn resolve_target(app: &App, target: &str) -> Result<(usize, Option<usize>), String> {
+    let (file_index, title) = match split_target(app, target)? {
+        (file_index, Some(title)) => (file_index, title),
+        (file_index, None) => return Ok((file_index, None)),
+    };
+    let titles = &app.lists[file_index].titles;
+    titles
+        .iter()
+        .position(|t| t == title)
+        .or_else(|| titles.iter().position(|t| t.eq_ignore_ascii_case(title)))
+        .map(|i| (file_index, Some(i)))
+        .ok_or_else(|| format!("No such list: {}", target))
+}
+
+fn select(app: &mut App, file_index: usize, list_index: usize) {
+    app.file_index = file_index;
+    app.list_index = list_index;
+    app.note_index = 0;
+    app.cursor_vertical = 1;
+}
+
+fn select_list(app: &mut App, target: &str) -> Result<(), String> {
+    match resolve_target(app, target)? {
+        (file_index, Some(list_index)) => {
+            select(app, file_index, list_index);
+            Ok(())
+        }
+        _ => Err(format!("Expected <file>/<list>, got: {}", target)),
+    }
+}
+
+/// Select a note of the current list by its number as shown by `ls` or by a unique
+/// case-insensitive part of its content
+fn select_note(app: &mut App, note: &str) -> Result<(), String> {
+    let notes = app.current_notes();
+    let paths = visible_notes(notes);
+    let index = match note.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= paths.len() => number - 1,
+        Ok(number) => return Err(format!("No note number {}", number)),
+        Err(_) => {
+            let pattern = note.to_lowercase();
+            let matches: Vec<usize> = paths
+                .iter()
+                .enumerate()
+                .filter(|(_, path)| {
+                    note_at(notes, path)
+                        .unwrap()
+                        .content
+                        .to_lowercase()
+                        .contains(&pattern)
+                })
+                .map(|(i, _)| i)
+                .collect();
+            match matches.len() {
+                0 => return Err(format!("No note matches \"{}\"", note)),
+                1 => matches[0],
+                _ => return Err(format!("\"{}\" matches {} notes", note, matches.len())),
+            }
+        }
+    };
+    app.note_index = index;
+    app.cursor_vertical = 2;
+    Ok(())
+}
+
+/// Numbered notes of the current list, optionally filtered by a tag or context and
+/// sorted by their metadata. Numbers always refer to the unsorted list.
+fn format_notes(app: &App, tag: Option<&str>, sort: Option<SortKey>) -> Vec<String> {
+    let notes = app.current_notes();
+    let mut entries: Vec<(usize, &Note)> = visible_notes(notes)
+        .iter()
+        .enumerate()
+        .map(|(i, path)| (i + 1, note_at(notes, path).unwrap()))
+        .filter(|(_, note)| tag.is_none_or(|tag| note.meta.matches_tag(tag)))
+        .collect();
+    if let Some(key) = sort {
+        entries.sort_by(|(_, a), (_, b)| compare(&a.meta, &b.meta, key));
+    }
+    entries
+        .iter()
+        .map(|(number, note)| {
+            let depth = if sort.is_some() { 0 } else { note.depth };
+            format!("{:>3}. {}{}", number, "  ".repeat(depth), note)
+        })
+        .collect()
+}
+
+/// `add <file>[/<list>] <content>`, the list is created if it does not exist yet
+pub fn add(app: &mut App, target: &str, content: &str) -> Result<String, String> {
+    let (file_index, list_index) = match split_target(app, target)? {
+        (file_index, Some(title)) => match resolve_target(app, target) {
+            Ok((_, Some(list_index))) => (file_index, list_index),
+            _ => {
+                select(app, file_index, 0);
+                app.mode = EditorMode::CreateList;
+                app.input.set(title);
+                app.handle_enter();
+                (file_index, app.lists[file_index].titles.len() - 1)
+            }
+        },
+        (file_index, None) if !app.lists[file_index].titles.is_empty() => (file_index, 0),
+        _ => return Err(format!("{} does not contain any lists", target)),
+    };
+    select(app, file_index, list_index);
+    app.mode = EditorMode::CreateNote;
+    app.input.set(content);
+    app.handle_enter();
+    Ok(String::new())
+}
+
+/// `ls [<file>[/<list>]] [--tag <tag>] [--sort <key>]`
+pub fn list(
+    app: &mut App,
+    target: Option<&str>,
+    tag: Option<&str>,
+    sort: Option<SortKey>,
+) -> Result<String, String> {
+    let mut lines: Vec<String> = Vec::new();
+    match target {
+        None => {
+            for (i, file) in app.files.iter().enumerate() {
+                lines.push(file.clone());
+                for title in app.lists[i].titles.iter() {
+                    lines.push(format!("  {}", title));
+                }
+            }
+        }
+        Some(target) => match resolve_target(app, target)? {
+            (file_index, Some(list_index)) => {
+                select(app, file_index, list_index);
+                lines.extend(format_notes(app, tag, sort));
+            }
+            (file_index, None) => {
+                for list_index in 0..app.lists[file_index].titles.len() {
+                    select(app, file_index, list_index);
+                    lines.push(format!("# {}", app.lists[file_index].titles[list_index]));
+                    lines.extend(format_notes(app, tag, sort));
+                }
+            }
+        },
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Done notes with a completion date on or after the given day, subtasks included
+fn completed_notes<'n>(
+    notes: &'n [Note],
+    since: NaiveDate,
+    found: &mut Vec<(NaiveDate, &'n Note)>,
+) {
+    for note in notes {
+        match note.meta.completed {
+            Some(date) if note.state == NoteEnum::DONE && date >= since => found.push((date, note)),
+            _ => {}
+        }
+        completed_notes(&note.children, since, found);
+    }
+}
+
+/// `completed [<file>[/<list>]] [--since <date>]` lists what was finished since a day, the
+/// Monday of this week by default, oldest first
+pub fn completed(app: &App, target: Option<&str>, since: Option<&str>) -> Result<String, String> {
+    let since = match since {
+        Some(date) => NaiveDate::parse_from_str(date, DATE_FORMAT)
+            .map_err(|_| format!("Expected a date like 2026-10-18, got: {}", date))?,
+        None => {
+            let today = Local::now().date_naive();
+            today - Duration::days(today.weekday().num_days_from_monday() as i64)
+        }
+    };
+    let lists: Vec<(usize, usize)> = match target {
+        None => (0..app.files.len())
+            .flat_map(|file_index| {
+                (0..app.lists[file_index].titles.len())
+                    .map(move |list_index| (file_index, list_index))
+            })
+            .collect(),
+        Some(target) => match resolve_target(app, target)? {
+            (file_index, Some(list_index)) => vec![(file_index, list_index)],
+            (file_index, None) => (0..app.lists[file_index].titles.len())
+                .map(|list_index| (file_index, list_index))
+                .collect(),
+        },
+    };
+
+    let mut entries: Vec<(NaiveDate, String)> = Vec::new();
+    for (file_index, list_index) in lists {
+        let mut found = Vec::new();
+        completed_notes(&app.lists[file_index].notes[list_index], since, &mut found);
+        entries.extend(found.into_iter().map(|(date, note)| {
+            (
+                date,
+                format!(
+                    "{} {}/{}: {}",
+                    date.format(DATE_FORMAT),
+                    app.files[file_index],
+                    app.lists[file_index].titles[list_index],
+                    note.content
+                ),
+            )
+        }));
+    }
+    if entries.is_empty() {
+        return Ok(format!(
+            "Nothing completed since {}",
+            since.format(DATE_FORMAT)
+        ));
+    }
+    entries.sort_by_key(|(date, _)| *date);
+    Ok(entries
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// `new <file> [--template <template>]` creates a file, from a template of the templates
+/// folder if one is given
+pub fn new_file(app: &mut App, name: &str, template: Option<&str>) -> Result<String, String> {
+    app.template = match template {
+        Some(template) => Some(app.find_template(template)?.ok_or_else(|| {
+            let names: Vec<String> = app
+                .load_templates()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|template| template.name)
+                .collect();
+            if names.is_empty() {
+                format!("No such template: {}", template)
+            } else {
+                format!(
+                    "No such template: {}, expected one of {}",
+                    template,
+                    names.join(", ")
+                )
+            }
+        })?),
+        None => None,
+    };
+    app.mode = EditorMode::CreateFile;
+    app.input.set(name);
+    app.handle_enter();
+    match app.message.take() {
+        Some(err) => Err(err),
+        None => Ok(String::new()),
+    }
+}
+
+/// `done <file>/<list> <note>`
+pub fn done(app: &mut App, target: &str, note: &str) -> Result<String, String> {
+    select_list(app, target)?;
+    select_note(app, note)?;
+    app.set_note_state(NoteEnum::DONE);
+    Ok(String::new())
+}
+
+/// `rm <file>[/<list>] [<note>]` removes a note, a whole list or a whole file
+pub fn remove(app: &mut App, target: &str, note: Option<&str>) -> Result<String, String> {
+    match (resolve_target(app, target)?, note) {
+        ((file_index, Some(list_index)), Some(note)) => {
+            select(app, file_index, list_index);
+            select_note(app, note)?;
+        }
+        ((file_index, Some(list_index)), None) => select(app, file_index, list_index),
+        ((file_index, None), None) => {
+            select(app, file_index, 0);
+            app.cursor_vertical = 0;
+        }
+        ((_, None), Some(_)) => return Err(format!("Expected <file>/<list>, got: {}", target)),
+    }
+    app.remove();
+    Ok(String::new())
+}
+
+/// `mv <file>/<list> <note> <file>/<list>` moves a note and its subtasks to another list
+pub fn move_note(
+    app: &mut App,
+    target: &str,
+    note: &str,
+    destination: &str,
+) -> Result<String, String> {
+    let (destination_file, destination_list) = match resolve_target(app, destination)? {
+        (file_index, Some(list_index)) => (file_index, list_index),
+        _ => return Err(format!("Expected <file>/<list>, got: {}", destination)),
+    };
+    select_list(app, target)?;
+    if (app.file_index, app.list_index) == (destination_file, destination_list) {
+        return Err(format!("The note is already in {}", destination));
+    }
+    select_note(app, note)?;
+    app.send_to(
+        Destination {
+            file_index: destination_file,
+            list_index: Some(destination_list),
+        },
+        false,
+    );
+    app.message = None;
+    Ok(String::new())
+}
+
+/// `export --format json` dumps every file of the data directory
+pub fn export_json(app: &App) -> Result<String, String> {
+    let data: Vec<FileData> = app
+        .files
+        .iter()
+        .zip(app.lists.iter())
+        .map(|(name, lists)| FileData {
+            name: name.clone(),
+            preamble: lists.preamble.clone(),
+            lists: lists.clone(),
+        })
+        .collect();
+    serde_json::to_string_pretty(&data).map_err(|err| err.to_string())
+}
+
+/// `import --format json` replaces the files contained in the input and creates missing ones
+pub fn import_json(app: &mut App, input: &str) -> Result<String, String> {
+    let data: Vec<FileData> = serde_json::from_str(input).map_err(|err| err.to_string())?;
+    for file in data.iter() {
+        validate_file_name(&file.name)?;
+    }
+
+    for mut file in data {
+        file.lists.preamble = file.preamble;
+        match app.files.iter().position(|name| *name == file.name) {
+            Some(i) => app.lists[i] = file.lists,
+            None => {
+                let path = file_path(app.main_path, &file.name);
+                app.files.push(file.name);
+                app.paths.push(path);
+                app.lists.push(file.lists);
+            }
+        }
+    }
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{add, done, export_json, import_json, list, move_note, remove};
+    use crate::todo::{file_path, parse_lines};
+    use crate::ui::app::App;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    const WORK: &str = "# Todo\n\n- [ ] write report\n  - [ ] draft\n- [ ] call Anna\n\n# Later\n\n- [ ] plan trip\n";
+
+    /// Run a subcommand on files given by name and content, returning its result and the
+    /// content of every file afterwards
+    fn run(
+        files: &[(&str, &str)],
+        command: impl FnOnce(&mut App) -> Result<String, String>,
+    ) -> (Result<String, String>, Vec<(String, String)>) {
+        let main_path = PathBuf::from("/nonexistent/yoku");
+        let mut names: Vec<String> = files.iter().map(|(name, _)| name.to_string()).collect();
+        let mut paths = names
+            .iter()
+            .map(|name| file_path(&main_path, name))
+            .collect();
+        let mut lists = files
+            .iter()
+            .map(|(_, content)| parse_lines(content.split('\n').map(String::from).collect()))
+            .collect();
+        let mut hashes = HashMap::new();
+        let mut to_remove = Vec::new();
+        let mut app = App::new(
+            &mut names,
+            &mut paths,
+            &mut lists,
+            &mut hashes,
+            &main_path,
+            &mut to_remove,
+        );
+        let result = command(&mut app);
+        let contents = app
+            .files
+            .iter()
+            .zip(app.lists.iter())
+            .map(|(name, list)| (name.clone(), list.to_string()))
+            .collect();
+        (result, contents)
+    }
+
+    #[test]
+    fn add_test() {
+        let (result, files) = run(&[("work", WORK)], |app| {
+            add(app, "work/later", "book hotel")
+        });
+        assert_eq!(result, Ok(String::new()));
+        assert!(files[0]
+            .1
+            .ends_with("# Later\n\n- [ ] plan trip\n- [ ] book hotel\n"));
+
+        let (_, files) = run(&[("work", WORK)], |app| {
+            add(app, "work/Someday", "learn Rust")
+        });
+        assert!(files[0]
+            .1
+            .ends_with("- [ ] plan trip\n\n# Someday\n- [ ] learn Rust\n"));
+        let (result, _) = run(&[("work", WORK)], |app| add(app, "home/Todo", "x"));
+        assert_eq!(result, Err("No such file: home/Todo".to_string()));
+    }
+
+    #[test]
+    fn list_test() {
+        let (result, _) = run(&[("work", WORK), ("home", "# Chores\n")], |app| {
+            list(app, None, None, None)
+        });
+        assert_eq!(result.unwrap(), "work\n  Todo\n  Later\nhome\n  Chores");
+        let (result, _) = run(&[("work", WORK)], |app| {
+            list(app, Some("work/Todo"), None, None)
+        });
+        assert_eq!(
+            result.unwrap(),
+            "  1. - [ ] write report\n  2.   - [ ] draft\n  3. - [ ] call Anna"
+        );
+    }
+
+    #[test]
+    fn done_test() {
+        let (result, files) = run(&[("work", WORK)], |app| done(app, "work/Todo", "anna"));
+        assert_eq!(result, Ok(String::new()));
+        assert!(files[0].1.contains("\n- [x] call Anna\n"));
+        let (_, files) = run(&[("work", WORK)], |app| done(app, "work/Todo", "1"));
+        assert!(files[0]
+            .1
+            .starts_with("# Todo\n\n- [x] write report\n  - [x] draft\n"));
+        let (result, _) = run(&[("work", WORK)], |app| done(app, "work/Todo", "r"));
+        assert_eq!(result, Err("\"r\" matches 2 notes".to_string()));
+    }
+
+    #[test]
+    fn remove_test() {
+        let (_, files) = run(&[("work", WORK)], |app| {
+            remove(app, "work/Todo", Some("report"))
+        });
+        assert_eq!(
+            files[0].1,
+            "# Todo\n\n- [ ] call Anna\n\n# Later\n\n- [ ] plan trip\n"
+        );
+        let (_, files) = run(&[("work", WORK)], |app| remove(app, "work/Todo", None));
+        assert_eq!(files[0].1, "# Later\n\n- [ ] plan trip\n");
+        let (_, files) = run(&[("work", WORK), ("home", "# Chores\n")], |app| {
+            remove(app, "work", None)
+        });
+        assert_eq!(files, vec![("home".to_string(), "# Chores\n".to_string())]);
+    }
+
+    #[test]
+    fn json_round_trip_test() {
+        let content = "Preamble text\n\n# T\n\nDesc\n\n- [ ] a\n  some detail line\n* bullet\n\
+            ```\ncode\n```\n- [x] b\n    - [ ] nested\n";
+        let (json, _) = run(&[("work", content)], |app| export_json(app));
+        let json = json.unwrap();
+        let (result, files) = run(&[("work", "# Old\n")], |app| import_json(app, &json));
+        assert_eq!(result, Ok(String::new()));
+        assert_eq!(files, vec![("work".to_string(), content.to_string())]);
+
+        let (_, files) = run(&[], |app| import_json(app, &json));
+        assert_eq!(files, vec![("work".to_string(), content.to_string())]);
+    }
+
+    #[test]
+    fn move_test() {
+        let (result, files) = run(
+            &[("work", WORK), ("home", "# Chores\n\n- [ ] dishes\n")],
+            |app| move_note(app, "work/Todo", "report", "home/Chores"),
+        );
+        assert_eq!(result, Ok(String::new()));
+        assert_eq!(
+            files[0].1,
+            "# Todo\n\n- [ ] call Anna\n\n# Later\n\n- [ ] plan trip\n"
+        );
+        assert_eq!(
+            files[1].1,
+            "# Chores\n\n- [ ] dishes\n- [ ] write report\n  - [ ] draft\n"
+        );
+        let (result, _) = run(&[("work", WORK)], |app| {
+            move_note(app, "work/Todo", "report", "work/Todo")
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::template::TEMPLATES_DIR;
+use crate::todo::{NoteEnum, STARTER_FILE_DESCRIPTION, STARTER_FILE_NOTE, STARTER_FILE_TITLE};
+use crate::ui::keymap::{Keymap, CALENDAR_KEYS, DIALOG_KEYS, NORMAL_KEYS, VISUAL_KEYS};
+use crate::ui::theme::Theme;
+use dirs::config_dir;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_DIR: &str = "yoku";
+pub const CONFIG_FILE: &str = "config.toml";
+pub const JOURNAL_TEMPLATE: &str = "# Tasks\n\n# Notes\n";
+
+/// Behavior which is not tied to keys or colors
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    /// States the state key cycles through, in order
+    pub cycle: Vec<NoteEnum>,
+    /// Save after every change instead of when quitting
+    pub autosave: bool,
+    /// Ask before removing a file or a list with notes
+    pub confirm_remove: bool,
+    /// Add `created:` to new notes and `done:` to completed notes
+    pub record_dates: bool,
+    /// Content of new journal days unless there is a `journal` template
+    pub journal_template: String,
+    /// List title, description and note of newly created files
+    pub new_file_title: String,
+    pub new_file_description: String,
+    pub new_file_note: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            cycle: vec![NoteEnum::OPEN, NoteEnum::DONE, NoteEnum::REJECTED],
+            autosave: false,
+            confirm_remove: true,
+            record_dates: false,
+            journal_template: JOURNAL_TEMPLATE.to_string(),
+            new_file_title: STARTER_FILE_TITLE.to_string(),
+            new_file_description: STARTER_FILE_DESCRIPTION.to_string(),
+            new_file_note: STARTER_FILE_NOTE.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub keys: Keymap,
+    pub visual_keys: Keymap,
+    pub dialog_keys: Keymap,
+    pub calendar_keys: Keymap,
+    pub theme: Theme,
+    /// Colors of the config file, applied on top of every built-in theme
+    pub colors: BTreeMap<String, String>,
+    pub options: Options,
+    /// Folder of the file and list templates
+    pub templates_dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            keys: Keymap::new(NORMAL_KEYS, &[]).unwrap(),
+            visual_keys: Keymap::new(VISUAL_KEYS, &[]).unwrap(),
+            dialog_keys: Keymap::new(DIALOG_KEYS, &[]).unwrap(),
+            calendar_keys: Keymap::new(CALENDAR_KEYS, &[]).unwrap(),
+            theme: Theme::default(),
+            colors: BTreeMap::new(),
+            options: Options::default(),
+            templates_dir: None,
+        }
+    }
+}
+
+impl Config {
+    /// Switch to a built-in theme, keeping the configured colors
+    pub fn set_theme(&mut self, name: &str) -> Result<(), String> {
+        let mut theme = Theme::preset(name)?;
+        for (name, value) in self.colors.iter() {
+            theme.set(name, value)?;
+        }
+        self.theme = theme;
+        Ok(())
+    }
+}
+
+/// One or several keys bound to an action
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Config file as written, checked by `parse_config`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    keys: BTreeMap<String, Keys>,
+    visual_keys: BTreeMap<String, Keys>,
+    dialog_keys: BTreeMap<String, Keys>,
+    calendar_keys: BTreeMap<String, Keys>,
+    theme: BTreeMap<String, String>,
+    options: Options,
+}
+
+fn key_overrides(keys: BTreeMap<String, Keys>) -> Vec<(String, Vec<String>)> {
+    keys.into_iter()
+        .map(|(action, keys)| match keys {
+            Keys::One(key) => (action, vec![key]),
+            Keys::Many(keys) => (action, keys),
+        })
+        .collect()
+}
+
+/// Parse the content of a config file, every problem found is reported
+pub fn parse_config(content: &str) -> Result<Config, Vec<String>> {
+    let file: ConfigFile = toml::from_str(content).map_err(|err| vec![err.to_string()])?;
+    let mut errors = Vec::new();
+
+    let mut keymap = |defaults, keys, section: &str| {
+        Keymap::new(defaults, &key_overrides(keys)).unwrap_or_else(|section_errors| {
+            errors.extend(
+                section_errors
+                    .into_iter()
+                    .map(|err| format!("[{}] {}", section, err)),
+            );
+            Keymap::new(defaults, &[]).unwrap()
+        })
+    };
+    let keys = keymap(NORMAL_KEYS, file.keys, "keys");
+    let visual_keys = keymap(VISUAL_KEYS, file.visual_keys, "visual_keys");
+    let dialog_keys = keymap(DIALOG_KEYS, file.dialog_keys, "dialog_keys");
+    let calendar_keys = keymap(CALENDAR_KEYS, file.calendar_keys, "calendar_keys");
+
+    let mut colors = file.theme;
+    let preset = colors.remove("preset");
+    let mut theme = Theme::default();
+    for (name, value) in colors.iter() {
+        if let Err(err) = theme.set(name, value) {
+            errors.push(format!("[theme] {}", err));
+        }
+    }
+
+    let options = file.options;
+    if options.cycle.is_empty() {
+        errors.push("[options] cycle needs at least one state".to_string());
+    }
+    for (i, state) in options.cycle.iter().enumerate() {
+        if options.cycle[..i].contains(state) {
+            errors.push(format!("[options] cycle contains {} twice", state.name()));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let mut config = Config {
+        keys,
+        visual_keys,
+        dialog_keys,
+        calendar_keys,
+        theme,
+        colors,
+        options,
+        templates_dir: None,
+    };
+    if let Some(preset) = preset {
+        config
+            .set_theme(&preset)
+            .map_err(|err| vec![format!("[theme] {}", err)])?;
+    }
+    Ok(config)
+}
+
+/// Default location of the config file in the platform's config directory
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+/// Read the config file. A missing file at the default location means the default config,
+/// a file passed explicitly has to exist. Templates are looked up next to the config file.
+pub fn load_config(path: Option<&Path>) -> Result<Config, String> {
+    let (path, explicit) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match config_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    let templates_dir = path.parent().map(|dir| dir.join(TEMPLATES_DIR));
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound && !explicit => {
+            return Ok(Config {
+                templates_dir,
+                ..Config::default()
+            })
+        }
+        Err(err) => return Err(format!("Could not read {}: {}", path.display(), err)),
+    };
+    let mut config = parse_config(&content).map_err(|errors| {
+        format!(
+            "Invalid config file {}:\n  {}",
+            path.display(),
+            errors.join("\n  ")
+        )
+    })?;
+    config.templates_dir = templates_dir;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::parse_config;
+    use crate::todo::NoteEnum;
+    use crate::ui::keymap::Action;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use tui::style::Color;
+
+    #[test]
+    fn config_test() {
+        let mut config = parse_config(
+            r##"
+            [keys]
+            done = "d"
+            right = ["Right", "Ctrl+l"]
+
+            [visual_keys]
+            mark = "m"
+
+            [dialog_keys]
+            keep_mine = "K"
+
+            [theme]
+            preset = "light"
+            background = "reset"
+            accent = "#ff8800"
+
+            [options]
+            cycle = ["open", "done"]
+            autosave = true
+            confirm_remove = false
+            "##,
+        )
+        .unwrap();
+        let key = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE);
+        assert_eq!(config.keys.get(key), Some(Action::Done));
+        let key = KeyEvent::new(KeyCode::Char('K'), KeyModifiers::SHIFT);
+        assert_eq!(config.dialog_keys.get(key), Some(Action::KeepMine));
+        assert_eq!(config.theme.name, "light");
+        assert_eq!(config.theme.accent, Color::Rgb(255, 136, 0));
+        config.set_theme("terminal").unwrap();
+        assert_eq!(config.theme.background, Color::Reset);
+        assert_eq!(config.theme.accent, Color::Rgb(255, 136, 0));
+        assert!(config.set_theme("sepia").is_err());
+        assert_eq!(config.options.cycle, vec![NoteEnum::OPEN, NoteEnum::DONE]);
+        assert!(config.options.autosave);
+        assert!(!config.options.confirm_remove);
+        assert_eq!(config.options.new_file_title, "Todo");
+
+        let errors = parse_config(
+            r#"
+            [keys]
+            fly = "f"
+            [calendar_keys]
+            done = "x"
+            [theme]
+            background = "plaid"
+            [options]
+            cycle = []
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert!(parse_config("[options]\nautosafe = true").is_err());
+        assert!(parse_config("").is_ok());
+    }
+}
@@ -1,5 +1,10 @@
 extern crate core;
 
+pub mod cli;
+pub mod config;
+pub mod merge;
+pub mod meta;
+pub mod template;
 pub mod todo;
 pub mod ui;
 pub mod util;
@@ -0,0 +1,241 @@
+use crate::todo::{is_fence, parse_lines, parse_note, parse_title, FileList};
+
+/// For every item of `a`, the index of the item of `b` it is matched with by the longest
+/// common subsequence
+fn matches(a: &[String], b: &[String]) -> Vec<Option<usize>> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut matched = vec![None; a.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            matched[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matched
+}
+
+/// Resolve a region changed on at least one side. Notes added on both sides are all kept,
+/// notes changed on both sides are conflicts.
+fn resolve(
+    base: &[String],
+    mine: &[String],
+    theirs: &[String],
+    merged: &mut Vec<String>,
+    conflicts: &mut Vec<String>,
+) {
+    if mine == base || mine == theirs {
+        merged.extend_from_slice(theirs);
+        return;
+    } else if theirs == base {
+        merged.extend_from_slice(mine);
+        return;
+    }
+    // Items at the same position changed on one side only, such as neighbouring notes
+    // checked on different sides
+    let mut i = 0;
+    while i < base.len() && i < mine.len() && i < theirs.len() {
+        if mine[i] == base[i] || mine[i] == theirs[i] {
+            merged.push(theirs[i].clone());
+        } else if theirs[i] == base[i] {
+            merged.push(mine[i].clone());
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    let (base, mine, theirs) = (&base[i..], &mine[i..], &theirs[i..]);
+    if base.is_empty() {
+        merged.extend_from_slice(mine);
+        merged.extend(theirs.iter().filter(|item| !mine.contains(item)).cloned());
+    } else {
+        conflicts.extend(
+            base.iter()
+                .map(|item| item.lines().next().unwrap_or("").to_string()),
+        );
+        merged.extend_from_slice(mine);
+    }
+}
+
+/// Three-way merge of items, changes of both sides relative to `base` are combined. Items
+/// changed differently on both sides are returned as conflicts.
+pub fn merge_items(
+    base: &[String],
+    mine: &[String],
+    theirs: &[String],
+) -> Result<Vec<String>, Vec<String>> {
+    let in_mine = matches(base, mine);
+    let in_theirs = matches(base, theirs);
+    let mut merged = Vec::new();
+    let mut conflicts = Vec::new();
+    let (mut b, mut m, mut t) = (0, 0, 0);
+    loop {
+        // Next base item kept by both sides
+        let stable = (b..base.len()).find_map(|i| match (in_mine[i], in_theirs[i]) {
+            (Some(mi), Some(ti)) => Some((i, mi, ti)),
+            _ => None,
+        });
+        match stable {
+            Some((i, mi, ti)) if i == b && mi == m && ti == t => {
+                merged.push(base[i].clone());
+                b += 1;
+                m += 1;
+                t += 1;
+            }
+            Some((i, mi, ti)) => {
+                resolve(
+                    &base[b..i],
+                    &mine[m..mi],
+                    &theirs[t..ti],
+                    &mut merged,
+                    &mut conflicts,
+                );
+                b = i;
+                m = mi;
+                t = ti;
+            }
+            None => {
+                resolve(
+                    &base[b..],
+                    &mine[m..],
+                    &theirs[t..],
+                    &mut merged,
+                    &mut conflicts,
+                );
+                break;
+            }
+        }
+    }
+    if conflicts.is_empty() {
+        Ok(merged)
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// Lines of a file grouped into items: a note together with the lines up to the next blank
+/// line which are not notes themselves, and every other line on its own
+fn items(list: &FileList) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    let (mut below_heading, mut in_note, mut in_fence) = (false, false, false);
+    for line in list.to_string().split('\n') {
+        if !in_fence && parse_title(line).is_some() {
+            below_heading = true;
+            in_note = false;
+            items.push(line.to_string());
+            continue;
+        }
+        if below_heading && !in_fence && parse_note(line).is_some() {
+            in_note = true;
+            items.push(line.to_string());
+            continue;
+        }
+        if is_fence(line) {
+            in_fence = !in_fence;
+        }
+        // Blank lines separate notes from what follows them, such as the next list
+        if !in_fence && line.trim().is_empty() {
+            in_note = false;
+        }
+        match items.last_mut() {
+            Some(note) if in_note => {
+                note.push('\n');
+                note.push_str(line);
+            }
+            _ => items.push(line.to_string()),
+        }
+    }
+    items
+}
+
+/// Three-way merge of a file by notes. Notes changed on only one side are merged, notes
+/// changed on both sides are returned as conflicts with the first line of their base version.
+pub fn merge_files(
+    base: &FileList,
+    mine: &FileList,
+    theirs: &FileList,
+) -> Result<FileList, Vec<String>> {
+    let merged = merge_items(&items(base), &items(mine), &items(theirs))?;
+    let content = merged.join("\n");
+    Ok(parse_lines(content.split('\n').map(String::from).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{merge_files, merge_items};
+    use crate::todo::parse_lines;
+
+    fn lines(content: &str) -> Vec<String> {
+        content.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn merge_items_test() {
+        let base = lines("a\nb\nc\nd\ne");
+        let mine = lines("a\nB\nc\nd\ne\nf");
+        let theirs = lines("x\na\nb\nc\ne");
+        assert_eq!(
+            merge_items(&base, &mine, &theirs),
+            Ok(lines("x\na\nB\nc\ne\nf"))
+        );
+
+        // Both sides changed the same item
+        let theirs = lines("a\nbb\nc\nd\ne");
+        assert_eq!(
+            merge_items(&base, &mine, &theirs),
+            Err(vec!["b".to_string()])
+        );
+
+        // Neighbours changed on different sides
+        let mine = lines("a\nB\nc\nd\ne");
+        let theirs = lines("a\nb\nC\nd\ne");
+        assert_eq!(
+            merge_items(&base, &mine, &theirs),
+            Ok(lines("a\nB\nC\nd\ne"))
+        );
+    }
+
+    #[test]
+    fn merge_files_test() {
+        let base = parse_lines(lines(
+            "# Todo\n\n- [ ] one\n  detail\n- [ ] two\n\n# Later\n",
+        ));
+        let mine = parse_lines(lines(
+            "# Todo\n\n- [x] one\n  detail\n- [ ] two\n\n# Later\n",
+        ));
+        let theirs = parse_lines(lines(
+            "# Todo\n\n- [ ] one\n  more detail\n- [ ] two\n- [ ] three\n\n# Later\n",
+        ));
+        // A note with its extra lines changed on both sides
+        assert_eq!(
+            merge_files(&base, &mine, &theirs).unwrap_err(),
+            vec!["- [ ] one".to_string()]
+        );
+
+        let theirs = parse_lines(lines(
+            "# Todo\n\n- [ ] one\n  detail\n- [-] two\n- [ ] three\n\n# Later\n- [ ] four\n",
+        ));
+        let mine = parse_lines(lines(
+            "# Todo\n\n- [x] one\n  detail\n- [ ] two\n- [ ] mine\n\n# Later\n",
+        ));
+        assert_eq!(
+            merge_files(&base, &mine, &theirs).unwrap().to_string(),
+            "# Todo\n\n- [x] one\n  detail\n- [-] two\n- [ ] mine\n- [ ] three\n\n# Later\n- [ ] four\n"
+        );
+    }
+}
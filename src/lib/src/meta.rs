@@ -0,0 +1,553 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl Priority {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interval {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+    Weekday(Weekday),
+}
+
+/// Rule of a recurring note: `every:week`, `every monday`, `rec:+1m` or `rec:2w`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Recurrence {
+    pub interval: Interval,
+    /// Whether the next date is counted from the due date, otherwise from the day the note
+    /// was completed. `every` and `rec:+` are strict.
+    pub strict: bool,
+}
+
+impl Recurrence {
+    /// Interval such as `week`, `weekly`, `monday`, `3d`, `2w`, `1m` or `1y`
+    fn parse_interval(text: &str) -> Option<Interval> {
+        let text = text.to_lowercase();
+        let interval = match text.as_str() {
+            "day" | "daily" => Interval::Days(1),
+            "week" | "weekly" => Interval::Weeks(1),
+            "month" | "monthly" => Interval::Months(1),
+            "year" | "yearly" => Interval::Years(1),
+            _ => {
+                if let Ok(weekday) = text.parse::<Weekday>() {
+                    return Some(Interval::Weekday(weekday));
+                }
+                let split = text.find(|c: char| !c.is_ascii_digit())?;
+                let count: u32 = text[..split].parse().ok().filter(|count| *count > 0)?;
+                match &text[split..] {
+                    "d" | "day" | "days" => Interval::Days(count),
+                    "w" | "week" | "weeks" => Interval::Weeks(count),
+                    "m" | "month" | "months" => Interval::Months(count),
+                    "y" | "year" | "years" => Interval::Years(count),
+                    _ => return None,
+                }
+            }
+        };
+        Some(interval)
+    }
+
+    /// Interval of the word after a separate `every`. Only `day`, `week`, `month`, `year` and
+    /// full weekday names count, so prose like `every sun` or `every 2nd` is no rule.
+    fn parse_every(word: &str) -> Option<Interval> {
+        let word = word.to_lowercase();
+        match word.as_str() {
+            "day" | "week" | "month" | "year" => Recurrence::parse_interval(&word),
+            // Weekdays parse from three letters or the full name
+            _ if word.len() > 3 => word.parse::<Weekday>().ok().map(Interval::Weekday),
+            _ => None,
+        }
+    }
+
+    /// Rule of a single word, `every` followed by a separate word is handled by
+    /// `Metadata::parse`
+    pub fn parse(word: &str) -> Option<Recurrence> {
+        if let Some(interval) = word.strip_prefix("every:") {
+            return Recurrence::parse_interval(interval).map(|interval| Recurrence {
+                interval,
+                strict: true,
+            });
+        }
+        let rule = word.strip_prefix("rec:")?;
+        let (rule, strict) = match rule.strip_prefix('+') {
+            Some(rule) => (rule, true),
+            None => (rule, false),
+        };
+        Recurrence::parse_interval(rule).map(|interval| Recurrence { interval, strict })
+    }
+
+    /// Date `times` intervals after `date`. Months are counted from `date` itself, so the 31st
+    /// falls on the 30th of November but still on the 31st of December.
+    fn advance(&self, date: NaiveDate, times: u32) -> NaiveDate {
+        let months = |months: u32| {
+            months
+                .checked_mul(times)
+                .and_then(|months| date.checked_add_months(Months::new(months)))
+        };
+        let times = times as i64;
+        let next = match self.interval {
+            Interval::Days(days) => date.checked_add_signed(Duration::days(days as i64 * times)),
+            Interval::Weeks(weeks) => {
+                date.checked_add_signed(Duration::weeks(weeks as i64 * times))
+            }
+            Interval::Months(count) => months(count),
+            Interval::Years(years) => months(years.saturating_mul(12)),
+            Interval::Weekday(weekday) => {
+                let days = (7 + weekday.num_days_from_monday() as i64
+                    - date.weekday().num_days_from_monday() as i64
+                    - 1)
+                    % 7
+                    + 1;
+                date.checked_add_signed(Duration::days(days + 7 * (times - 1)))
+            }
+        };
+        next.unwrap_or(date)
+    }
+
+    /// Due date of the next occurrence. Strict rules step from the due date until they pass
+    /// today, so a missed occurrence is not repeated.
+    pub fn next(&self, due: Option<NaiveDate>, today: NaiveDate) -> NaiveDate {
+        match due {
+            Some(due) if self.strict => {
+                let mut times = 1;
+                let mut next = self.advance(due, times);
+                while next <= today {
+                    times += 1;
+                    let after = self.advance(due, times);
+                    if after <= next {
+                        break;
+                    }
+                    next = after;
+                }
+                next
+            }
+            _ => self.advance(today, 1),
+        }
+    }
+}
+
+/// Metadata written inline in the content of a note
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contexts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<NaiveDate>,
+    #[serde(skip)]
+    pub recurrence: Option<Recurrence>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    /// `due:2026-11-01`
+    Due(NaiveDate),
+    /// `!high`, `!medium`, `!low` or `(A)`, `(B)`, `(C)` as the first word
+    Priority(Priority),
+    /// `#tag`
+    Tag(String),
+    /// `@context`
+    Context(String),
+    /// `created:2026-10-18`
+    Created(NaiveDate),
+    /// `done:2026-10-18` or `✓2026-10-18`
+    Completed(NaiveDate),
+    /// `every:week` or `rec:+1m`
+    Recurrence(Recurrence),
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '/')
+}
+
+/// Parse a single whitespace separated word of a note
+pub fn parse_token(word: &str, first: bool) -> Option<Token> {
+    let date = |date: &str| NaiveDate::parse_from_str(date, DATE_FORMAT).ok();
+    if let Some(rest) = word.strip_prefix("due:") {
+        return date(rest).map(Token::Due);
+    }
+    if let Some(rest) = word.strip_prefix("created:") {
+        return date(rest).map(Token::Created);
+    }
+    if let Some(rest) = word
+        .strip_prefix("done:")
+        .or_else(|| word.strip_prefix('✓'))
+    {
+        return date(rest).map(Token::Completed);
+    }
+    if let Some(recurrence) = Recurrence::parse(word) {
+        return Some(Token::Recurrence(recurrence));
+    }
+    if let Some(name) = word.strip_prefix('#') {
+        return is_name(name).then(|| Token::Tag(name.to_string()));
+    }
+    if let Some(name) = word.strip_prefix('@') {
+        return is_name(name).then(|| Token::Context(name.to_string()));
+    }
+    let priority = match word.to_lowercase().as_str() {
+        "!high" => Priority::High,
+        "!medium" => Priority::Medium,
+        "!low" => Priority::Low,
+        "(a)" if first => Priority::High,
+        "(b)" if first => Priority::Medium,
+        "(c)" if first => Priority::Low,
+        _ => return None,
+    };
+    Some(Token::Priority(priority))
+}
+
+impl Metadata {
+    pub fn parse(content: &str) -> Self {
+        let mut meta = Metadata::default();
+        let words: Vec<&str> = content.split_whitespace().collect();
+        for (i, word) in words.iter().enumerate() {
+            // `every monday` spans two words
+            if word.eq_ignore_ascii_case("every") && meta.recurrence.is_none() {
+                meta.recurrence = words
+                    .get(i + 1)
+                    .and_then(|next| Recurrence::parse_every(next))
+                    .map(|interval| Recurrence {
+                        interval,
+                        strict: true,
+                    });
+            }
+            match parse_token(word, i == 0) {
+                Some(Token::Due(date)) => meta.due = meta.due.or(Some(date)),
+                Some(Token::Priority(priority)) => meta.priority = meta.priority.or(Some(priority)),
+                Some(Token::Tag(tag)) => meta.tags.push(tag),
+                Some(Token::Context(context)) => meta.contexts.push(context),
+                Some(Token::Created(date)) => meta.created = meta.created.or(Some(date)),
+                Some(Token::Completed(date)) => meta.completed = meta.completed.or(Some(date)),
+                Some(Token::Recurrence(recurrence)) => {
+                    meta.recurrence = meta.recurrence.or(Some(recurrence))
+                }
+                None => {}
+            }
+        }
+        meta
+    }
+
+    /// Whether a `#tag`, `@context` or bare name filter applies
+    pub fn matches_tag(&self, filter: &str) -> bool {
+        let tag = |name: &str| self.tags.iter().any(|tag| tag.eq_ignore_ascii_case(name));
+        let context = |name: &str| {
+            self.contexts
+                .iter()
+                .any(|context| context.eq_ignore_ascii_case(name))
+        };
+        if let Some(name) = filter.strip_prefix('#') {
+            tag(name)
+        } else if let Some(name) = filter.strip_prefix('@') {
+            context(name)
+        } else {
+            tag(filter) || context(filter)
+        }
+    }
+}
+
+/// Whitespace separated words with their byte offsets
+fn words(content: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in content.char_indices().chain([(content.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (true, Some(word_start)) => {
+                words.push((word_start, &content[word_start..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    words
+}
+
+/// Content with the first word for which `is_token` holds replaced by `token` and the other
+/// ones removed, `token` is added at the end if there is none. `None` only removes them. The
+/// rest of the content keeps its spacing.
+fn replace_token(
+    content: &str,
+    is_token: impl Fn(usize, &str) -> bool,
+    token: Option<&str>,
+) -> String {
+    let mut result = String::new();
+    let mut copied = 0;
+    let mut replaced = false;
+    for (i, (start, word)) in words(content).into_iter().enumerate() {
+        if !is_token(i, word) {
+            continue;
+        }
+        match token {
+            Some(token) if !replaced => {
+                result.push_str(&content[copied..start]);
+                result.push_str(token);
+                replaced = true;
+            }
+            // The word goes with the whitespace before it, or after it at the start
+            _ => result.push_str(content[copied..start].trim_end()),
+        }
+        copied = start + word.len();
+        if result.is_empty() {
+            copied = content.len() - content[copied..].trim_start().len();
+        }
+    }
+    result.push_str(&content[copied..]);
+    match token {
+        Some(token) if !replaced && result.trim().is_empty() => token.to_string(),
+        Some(token) if !replaced => format!("{} {}", result.trim_end(), token),
+        _ => result,
+    }
+}
+
+/// Content with the priority token replaced by `!<priority>`, `None` removes it
+pub fn with_priority(content: &str, priority: Option<Priority>) -> String {
+    let token = priority.map(|priority| format!("!{}", priority.name()));
+    replace_token(
+        content,
+        |i, word| matches!(parse_token(word, i == 0), Some(Token::Priority(_))),
+        token.as_deref(),
+    )
+}
+
+/// Content with the completion date replaced by `done:<date>`, `None` removes it
+pub fn with_completed(content: &str, date: Option<NaiveDate>) -> String {
+    let token = date.map(|date| format!("done:{}", date.format(DATE_FORMAT)));
+    replace_token(
+        content,
+        |_, word| matches!(parse_token(word, false), Some(Token::Completed(_))),
+        token.as_deref(),
+    )
+}
+
+/// Content with the due date replaced by `due:<date>`
+pub fn with_due(content: &str, date: NaiveDate) -> String {
+    let token = format!("due:{}", date.format(DATE_FORMAT));
+    replace_token(
+        content,
+        |_, word| matches!(parse_token(word, false), Some(Token::Due(_))),
+        Some(&token),
+    )
+}
+
+/// Content with `created:<date>` added at the end unless it has a creation date already
+pub fn with_created(content: &str, date: NaiveDate) -> String {
+    if Metadata::parse(content).created.is_some() {
+        content.to_string()
+    } else {
+        format!(
+            "{} created:{}",
+            content.trim_end(),
+            date.format(DATE_FORMAT)
+        )
+    }
+}
+
+/// `today`, `yesterday`, `3 days ago` or `in 3 days`
+pub fn relative_date(date: NaiveDate, today: NaiveDate) -> String {
+    match (today - date).num_days() {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        -1 => "tomorrow".to_string(),
+        days if days > 0 => format!("{} days ago", days),
+        days => format!("in {} days", -days),
+    }
+}
+
+/// Content with a `#tag` or `@context` added at the end unless present, or removed
+pub fn with_tag(content: &str, tag: &str, add: bool) -> String {
+    let present = content
+        .split_whitespace()
+        .any(|word| word.eq_ignore_ascii_case(tag));
+    if add && present {
+        content.to_string()
+    } else if add {
+        format!("{} {}", content.trim_end(), tag)
+    } else {
+        replace_token(content, |_, word| word.eq_ignore_ascii_case(tag), None)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SortKey {
+    Priority,
+    Due,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "priority" => Ok(SortKey::Priority),
+            "due" => Ok(SortKey::Due),
+            _ => Err(format!("Unknown sort key: {}", s)),
+        }
+    }
+}
+
+/// Compare metadata, entries without a value are ordered last
+pub fn compare(a: &Metadata, b: &Metadata, key: SortKey) -> Ordering {
+    fn last_if_none<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+    match key {
+        SortKey::Priority => last_if_none(a.priority, b.priority).then(last_if_none(a.due, b.due)),
+        SortKey::Due => last_if_none(a.due, b.due).then(last_if_none(a.priority, b.priority)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meta::{
+        relative_date, with_completed, with_created, with_due, with_priority, with_tag, Interval,
+        Metadata, Priority, Recurrence,
+    };
+    use chrono::{NaiveDate, Weekday};
+
+    #[test]
+    fn parse_test() {
+        let meta = Metadata::parse("(A) call bob due:2026-11-01 #work @phone due:2027-01-01 (B)");
+        assert_eq!(meta.priority, Some(Priority::High));
+        assert_eq!(meta.due, NaiveDate::from_ymd_opt(2026, 11, 1));
+        assert_eq!(meta.tags, vec!["work"]);
+        assert_eq!(meta.contexts, vec!["phone"]);
+        assert!(meta.matches_tag("#WORK") && meta.matches_tag("phone"));
+        assert!(!meta.matches_tag("@work"));
+
+        let meta = Metadata::parse("issue # 12 due:tomorrow !LOW");
+        assert_eq!(meta.priority, Some(Priority::Low));
+        assert_eq!(meta.due, None);
+        assert!(meta.tags.is_empty());
+    }
+
+    #[test]
+    fn edit_test() {
+        assert_eq!(
+            with_priority("(A) call bob !low", Some(Priority::Medium)),
+            "!medium call bob"
+        );
+        assert_eq!(
+            with_priority("call  bob\t!low  @phone", None),
+            "call  bob  @phone"
+        );
+        assert_eq!(
+            with_priority("call  bob", Some(Priority::High)),
+            "call  bob !high"
+        );
+        assert_eq!(with_priority("call (A) bob", None), "call (A) bob");
+        assert_eq!(with_tag("call bob", "#work", true), "call bob #work");
+        assert_eq!(with_tag("call bob #Work", "#work", true), "call bob #Work");
+        assert_eq!(with_tag("call #work bob", "#work", false), "call bob");
+        assert_eq!(with_tag("#work call  bob", "#work", false), "call  bob");
+
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        assert_eq!(
+            with_completed("call ✓2026-10-01 bob", Some(date)),
+            "call done:2026-10-18 bob"
+        );
+        assert_eq!(with_completed("call bob done:2026-10-18", None), "call bob");
+        assert_eq!(
+            with_created("call bob", date),
+            "call bob created:2026-10-18"
+        );
+        let meta = Metadata::parse("call bob created:2026-10-01 ✓2026-10-18");
+        assert_eq!(meta.created, NaiveDate::from_ymd_opt(2026, 10, 1));
+        assert_eq!(meta.completed, Some(date));
+        assert_eq!(relative_date(date, date), "today");
+        assert_eq!(
+            relative_date(NaiveDate::from_ymd_opt(2026, 10, 15).unwrap(), date),
+            "3 days ago"
+        );
+    }
+
+    #[test]
+    fn recurrence_test() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = date(2026, 10, 18);
+        let meta = Metadata::parse("review every monday");
+        assert_eq!(
+            meta.recurrence.map(|rule| rule.interval),
+            Some(Interval::Weekday(Weekday::Mon))
+        );
+        assert_eq!(
+            meta.recurrence.unwrap().next(None, today),
+            date(2026, 10, 19)
+        );
+
+        let invoice = Recurrence::parse("rec:+1m").unwrap();
+        assert!(invoice.strict);
+        assert_eq!(
+            invoice.next(Some(date(2026, 10, 31)), today),
+            date(2026, 11, 30)
+        );
+        // Missed occurrences are counted from the due date, not from the shorter month
+        assert_eq!(
+            invoice.next(Some(date(2026, 10, 31)), date(2026, 12, 15)),
+            date(2026, 12, 31)
+        );
+        let weekly = Recurrence::parse("every:week").unwrap();
+        assert_eq!(
+            weekly.next(Some(date(2026, 9, 30)), today),
+            date(2026, 10, 21)
+        );
+        let relaxed = Recurrence::parse("rec:2w").unwrap();
+        assert_eq!(
+            relaxed.next(Some(date(2026, 9, 30)), today),
+            date(2026, 11, 1)
+        );
+        for prose in [
+            "meet every sun",
+            "every 2nd week",
+            "every 3d",
+            "every daily",
+            "every",
+        ] {
+            assert_eq!(Metadata::parse(prose).recurrence, None);
+        }
+        assert!(Metadata::parse("water plants every Day")
+            .recurrence
+            .is_some());
+        assert_eq!(Recurrence::parse("rec:3x"), None);
+        assert_eq!(Recurrence::parse("every:0d"), None);
+        assert_eq!(
+            with_due("pay due:2026-10-01 rec:1m", date(2026, 11, 1)),
+            "pay due:2026-11-01 rec:1m"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use crate::meta::DATE_FORMAT;
+use crate::todo::{parse_lines, FileList, Note};
+use chrono::{Datelike, NaiveDate};
+use std::fs;
+use std::path::Path;
+
+/// Folder of the templates, next to the config file
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Markdown file of the templates folder used to seed new files and lists
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    /// File name without `.md`
+    pub name: String,
+    pub content: String,
+}
+
+impl Template {
+    /// Content with `{{date}}`, `{{name}}` and `{{week}}` replaced
+    pub fn expand(&self, name: &str, date: NaiveDate) -> String {
+        expand(&self.content, name, date)
+    }
+
+    /// Lists of a new file created from the template
+    pub fn file_list(&self, name: &str, date: NaiveDate) -> FileList {
+        let content = self.expand(name, date);
+        parse_lines(content.split('\n').map(String::from).collect())
+    }
+
+    /// Description and notes of a new list created from the template, taken from its first
+    /// list. Text before the first heading is left out like the preamble of a file, a template
+    /// without a heading is a single list.
+    pub fn list(&self, name: &str, date: NaiveDate) -> (String, Vec<Note>) {
+        let mut list = self.file_list(name, date);
+        if list.titles.is_empty() {
+            let content = format!("# {}\n{}", name, self.expand(name, date));
+            list = parse_lines(content.split('\n').map(String::from).collect());
+        }
+        (list.descriptions.swap_remove(0), list.notes.swap_remove(0))
+    }
+}
+
+/// Replace the variables of a template. `{{name}}` is the name of the new file or list,
+/// `{{date}}` today as `YYYY-MM-DD` and `{{week}}` the ISO week number of today.
+pub fn expand(content: &str, name: &str, date: NaiveDate) -> String {
+    let mut result = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let value = match rest[start + 2..end].trim() {
+            "date" => date.format(DATE_FORMAT).to_string(),
+            "name" => name.to_string(),
+            "week" => date.iso_week().week().to_string(),
+            _ => rest[start..end + 2].to_string(),
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(&value);
+        rest = &rest[end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Templates of a folder sorted by name, a missing folder has none
+pub fn load_templates(dir: &Path) -> Result<Vec<Template>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) if !dir.exists() => return Ok(Vec::new()),
+        Err(err) => return Err(format!("Could not read {}: {}", dir.display(), err)),
+    };
+    let mut templates = Vec::new();
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) if path.extension().is_some_and(|ext| ext == "md") => name,
+            _ => continue,
+        };
+        if name.starts_with('.') || !path.is_file() {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        templates.push(Template {
+            name: name.to_string(),
+            content,
+        });
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::{expand, load_templates, Template};
+    use chrono::NaiveDate;
+    use std::fs;
+
+    #[test]
+    fn template_test() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        assert_eq!(
+            expand(
+                "# {{name}} {{ week }}\n{{date}} {{other}} {{",
+                "sprint",
+                date
+            ),
+            "# sprint 42\n2026-10-18 {{other}} {{"
+        );
+
+        let template = Template {
+            name: "sprint".to_string(),
+            content: "# Sprint {{week}}\n\nStarted {{date}}\n\n- [ ] plan {{name}}\n\n# Done\n"
+                .to_string(),
+        };
+        let list = template.file_list("backend", date);
+        assert_eq!(list.titles, vec!["Sprint 42", "Done"]);
+        assert_eq!(list.descriptions[0], "Started 2026-10-18");
+        assert_eq!(list.notes[0][0].content, "plan backend");
+        let (description, notes) = template.list("backend", date);
+        assert_eq!(description, "Started 2026-10-18");
+        assert_eq!(notes.len(), 1);
+        let checklist = Template {
+            name: "checklist".to_string(),
+            content: "#review\n- [ ] test {{name}}\n".to_string(),
+        };
+        let (description, notes) = checklist.list("release", date);
+        assert_eq!(description, "#review");
+        assert_eq!(notes[0].content, "test release");
+        let steps = Template {
+            name: "steps".to_string(),
+            content: "## Steps\n- [ ] build\n".to_string(),
+        };
+        let (description, notes) = steps.list("release", date);
+        assert_eq!(description, "## Steps");
+        assert_eq!(notes[0].content, "build");
+        let release = Template {
+            name: "release".to_string(),
+            content: "Checklist for {{name}}\n\n# Build\n- [ ] tag\n# Ship\n- [ ] upload\n"
+                .to_string(),
+        };
+        let (description, notes) = release.list("1.0", date);
+        assert_eq!(description, "");
+        assert_eq!(notes[0].content, "tag");
+        assert_eq!(release.file_list("1.0", date).titles, vec!["Build", "Ship"]);
+
+        let dir = std::env::temp_dir().join(format!("yoku-templates-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("weekly.md"), "# Week {{week}}\n").unwrap();
+        fs::write(dir.join("sprint.md"), "# Sprint\n").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        let templates = load_templates(&dir).unwrap();
+        let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["sprint", "weekly"]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(load_templates(&dir), Ok(Vec::new()));
+    }
+}
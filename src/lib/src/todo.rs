@@ -1,90 +1,390 @@
-use crate::ui::app::EMPTY_NOTE_VEC;
+use crate::meta::{compare, with_completed, with_due, Metadata, SortKey, DATE_FORMAT};
+use crate::ui::app::{EMPTY_NOTE_VEC, EMPTY_STRING};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 use std::process::exit;
 use std::{
     fmt,
-    fs::File,
-    io::{prelude::*, BufReader},
-    path::Path,
+    fs::{self, File},
+    io::{self, prelude::*, BufReader},
+    path::{Path, PathBuf},
 };
 
 pub const MAIN_DIR: &str = "yoku";
 pub const STARTER_FILE: &str = "tutorial.md";
+/// Lists the files of the data directory in the order they are shown
+pub const ORDER_FILE: &str = ".order";
 pub const STARTER_FILE_CONTENT: &str =
-    "# Start\n\nThis is a simple todo list\n\n- [ ] you may change note state with Enter, Spacebar, x, +, - or delete it with r\n- [ ] navigation keys include WASD, HJKL and arrow keys\n\
-    \n# Create\n\nThis list contains shortcuts related to creating new files\n\n- [ ] u = create new file (press enter to confirm)\n- [ ] i = create new list (press enter to confirm)\n- [ ] o = create new note (press enter to confirm)\n\
-    \n# Modify\n\nThis list contains shortcuts related to modifying data\n\n- [ ] e = edit current file/note/list\n- [ ] Ctrl + e = edit current list's description \n- [ ] r = remove current file/note/list\n- [ ] use the Escape key to unselect the current note\n\
-    \n# Exiting\n\n- [ ] q = exit and save\n- [ ] Ctrl + q  = exit and discard changes\n- [ ] Ctrl + C  = exit and discard changes\n\n";
+    "# Start\n\nThis is a simple todo list\n\n- [ ] you may change note state with Enter, Spacebar, x, +, - or delete it with r\n- [/] t = in progress, > = deferred, ! = blocked\n- [ ] navigation keys include WASD, HJKL and arrow keys\n- [ ] T = switch between the dark, light, high contrast and terminal themes\n- [ ] ? or F1 = show the keys of the current mode, the status bar at the bottom shows the mode and unsaved changes\n- [ ] c = today's journal day, back to your files with c again, C = calendar of journal days\n\
+    \n# Create\n\nThis list contains shortcuts related to creating new files\n\n- [ ] u = create new file (press enter to confirm), folder/name creates it in a folder\n- [ ] U = create new folder (press enter to confirm)\n- [ ] i = create new list (press enter to confirm)\n- [ ] o = create new note (press enter to confirm)\n- [ ] with templates in the templates folder next to the config file, new files and lists offer a template to start from\n\
+    \n# Modify\n\nThis list contains shortcuts related to modifying data\n\n- [ ] e = edit current file/note/list\n- [ ] Ctrl + e = edit current list's description, Alt + Enter or Ctrl + j starts a new line\n- [ ] E = edit current file/list/note in $VISUAL or $EDITOR\n- [ ] r = remove current file/note/list\n- [ ] Tab / Shift + Tab = indent/outdent current note\n- [ ] J / K = move current file/list/note down/up\n- [ ] y / X / p = yank/cut/paste current note or list\n- [ ] m / M = move/copy current note or list to another list or file\n- [ ] v = visual mode, mark notes with v / Space, V for a range, a for all or O / D / R by state\n- [ ] in visual mode o / x / -, 1-3 / 0 = priority, # = tags, r, y, X and m apply to every marked note\n- [ ] z = collapse/expand subtasks of current note\n- [ ] S / D = sort notes by priority/due date\n- [ ] / = search notes, n / N = next/previous match, f = list all matches\n- [ ] Ctrl + z / Ctrl + y = undo/redo\n- [ ] use the Escape key to unselect the current note\n\
+    \n# Metadata\n\nNotes may contain a due date, a priority, tags and contexts\n\n- [ ] due:2030-01-01 sets a due date\n- [ ] !high, !medium, !low or (A), (B), (C) at the start set a priority\n- [ ] #tag and @context mark related notes\n- [ ] created:2030-01-01 and done:2030-01-01 tell when a note was created and completed\n- [ ] every:week, every monday or rec:+1m repeat a note, completing it adds the next one\n\
+    \n# Exiting\n\n- [ ] Ctrl + s = save\n- [ ] q = exit and save\n- [ ] Ctrl + q / Ctrl + C = exit, unsaved changes are saved or discarded after asking\n- [ ] removing a file or a list with notes asks first, y confirms\n\n";
+
+/// Indentation of subtasks in files which have none yet
+pub const DEFAULT_INDENT: &str = "  ";
 
 pub const STARTER_FILE_TITLE: &str = "Todo";
 pub const STARTER_FILE_DESCRIPTION: &str = "This is a simple todo list";
 pub const STARTER_FILE_NOTE: &str = "you may check this";
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(into = "Vec<ListData>", from = "Vec<ListData>")]
 pub struct FileList {
+    /// Lines preceding the first heading
+    pub preamble: Vec<String>,
     pub titles: Vec<String>,
     pub descriptions: Vec<String>,
     pub notes: Vec<Vec<Note>>,
+    pub layouts: Vec<ListLayout>,
+}
+
+/// Source lines of a list which are neither its title, description nor notes.
+/// Keeping them allows unmodified parts of a file to be written back verbatim.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListLayout {
+    /// Heading line as found in the file, empty for lists created by yoku
+    pub heading: String,
+    /// Blank lines between the heading and the description
+    pub before_description: Vec<String>,
+    /// Blank lines between the description and the first note
+    pub after_description: Vec<String>,
+    /// Blank lines between the last note and the next heading
+    pub trailing: Vec<String>,
+    /// Whitespace of one level of subtasks, used for notes which are indented anew
+    pub indent: String,
+}
+
+impl Default for ListLayout {
+    fn default() -> Self {
+        ListLayout {
+            heading: String::new(),
+            before_description: vec![],
+            after_description: vec![String::new()],
+            trailing: vec![String::new()],
+            indent: DEFAULT_INDENT.to_string(),
+        }
+    }
 }
 
 impl FileList {
     pub fn remove(&mut self, index: usize) {
-        if self.titles.len() <= index + 1 {
+        if index < self.titles.len() {
             self.titles.remove(index);
         }
-        if self.descriptions.len() <= index + 1 {
+        if index < self.descriptions.len() {
             self.descriptions.remove(index);
         }
-        if self.notes.len() <= index + 1 {
+        if index < self.notes.len() {
             self.notes.remove(index);
         }
+        if index < self.layouts.len() {
+            self.layouts.remove(index);
+        }
+    }
+    pub fn push_list(&mut self, title: String, description: String, notes: Vec<Note>) {
+        let mut list = FileList::default();
+        list.titles.push(title);
+        list.descriptions.push(description);
+        list.notes.push(notes);
+        list.layouts.push(ListLayout::default());
+        self.insert_lists(self.titles.len(), list);
+    }
+    /// Copy of a single list, including its layout
+    pub fn extract(&self, index: usize) -> FileList {
+        FileList {
+            preamble: vec![],
+            titles: vec![self.titles[index].clone()],
+            descriptions: vec![self.descriptions.get(index).cloned().unwrap_or_default()],
+            notes: vec![self.notes.get(index).cloned().unwrap_or_default()],
+            layouts: vec![self.layouts.get(index).cloned().unwrap_or_default()],
+        }
+    }
+    /// Insert every list of another file at the given index, its preamble is dropped
+    pub fn insert_lists(&mut self, index: usize, lists: FileList) {
+        // Empty files parse into a single blank preamble line
+        if self.titles.is_empty() && self.preamble.iter().all(|line| line.is_empty()) {
+            self.preamble.clear();
+        }
+        for (i, title) in lists.titles.into_iter().enumerate() {
+            let mut layout = lists.layouts.get(i).cloned().unwrap_or_default();
+            // Lists taken from the end of a file may lack the blank line separating them
+            if layout.trailing.is_empty() {
+                layout.trailing.push(String::new());
+            }
+            self.titles.insert(index + i, title);
+            self.descriptions.insert(
+                index + i,
+                lists.descriptions.get(i).cloned().unwrap_or_default(),
+            );
+            self.notes
+                .insert(index + i, lists.notes.get(i).cloned().unwrap_or_default());
+            self.layouts.insert(index + i, layout);
+        }
+    }
+    /// Swap two lists together with their descriptions, notes and layout
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.titles.swap(a, b);
+        self.descriptions.swap(a, b);
+        self.notes.swap(a, b);
+        if a < self.layouts.len() && b < self.layouts.len() {
+            self.layouts.swap(a, b);
+            // Blank lines separating the lists stay in place
+            let trailing = std::mem::take(&mut self.layouts[a].trailing);
+            self.layouts[a].trailing = std::mem::replace(&mut self.layouts[b].trailing, trailing);
+        }
     }
-    pub fn write(&self, path: &Path) {
-        let mut write_string = self.to_string();
-        // Add trailing newline
-        write_string.push('\n');
+    /// Write the file without ever leaving it truncated, the replaced version is kept as a
+    /// backup
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if path.exists() {
+            fs::copy(path, backup_path(path))?;
+        } else if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_atomic(path, self.to_string().as_bytes())
+    }
+}
 
-        let mut file = File::open(&path).unwrap_or_else(|_| {
-            File::create(&path).unwrap_or_else(|_| panic!("Could not create file {:?}", path))
-        });
+/// Write to a temporary file next to the destination which then replaces it
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp_path = temp_path(path);
+    let res = write_synced(&tmp_path, content).and_then(|_| fs::rename(&tmp_path, path));
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    res?;
+    sync_dir(path);
+    Ok(())
+}
+
+fn write_synced(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// Make the rename durable
+#[cfg(unix)]
+fn sync_dir(path: &Path) {
+    if let Some(dir) = path.parent() {
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    }
+}
+
+/// Directories cannot be opened for syncing on other platforms
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) {}
+
+fn with_file_name(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}{}{}", prefix, name, suffix))
+}
+
+/// `todo.md` is saved through `.todo.md.tmp`
+pub fn temp_path(path: &Path) -> PathBuf {
+    with_file_name(path, ".", ".tmp")
+}
 
-        file.write_all(write_string.as_bytes())
-            .unwrap_or_else(|_| panic!("Could not write to file {:?}", path));
+/// `todo.md` keeps its previous version in `todo.md.bak`
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_file_name(path, "", ".bak")
+}
+
+fn order_entry(main_path: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(main_path).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Sort files as listed in the order file of the data directory. Files which are not listed
+/// keep their order and follow the listed ones.
+pub fn apply_order(main_path: &Path, paths: &mut [PathBuf]) {
+    let content = match fs::read_to_string(main_path.join(ORDER_FILE)) {
+        Ok(content) => content,
+        Err(_) => return,
+    };
+    let entries: Vec<&str> = content.lines().collect();
+    paths.sort_by_key(|path| {
+        let entry = order_entry(main_path, path);
+        entries
+            .iter()
+            .position(|listed| *listed == entry)
+            .unwrap_or(usize::MAX)
+    });
+}
+
+/// Persist the order of files. Nothing is written as long as files are in alphabetical
+/// order and no order file exists yet.
+pub fn write_order(main_path: &Path, paths: &[PathBuf]) -> io::Result<()> {
+    let order_path = main_path.join(ORDER_FILE);
+    let existing = fs::read_to_string(&order_path).ok();
+    if existing.is_none() && paths.windows(2).all(|pair| pair[0] <= pair[1]) {
+        return Ok(());
+    }
+    let mut content = String::new();
+    for path in paths {
+        content.push_str(&order_entry(main_path, path));
+        content.push('\n');
+    }
+    if existing.as_deref() == Some(content.as_str()) {
+        return Ok(());
+    }
+    write_atomic(&order_path, content.as_bytes())
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_none_or(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Whether a data directory entry is a todo file rather than a hidden, backup, temporary or
+/// other non Markdown file
+pub fn is_todo_file(path: &Path) -> bool {
+    let markdown = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+    path.is_file() && markdown && !is_hidden(path)
+}
+
+/// Todo files of the data directory and its folders, sorted by path. Hidden folders are
+/// skipped.
+pub fn todo_files(main_path: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![main_path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.is_dir() && !is_hidden(&path) {
+                dirs.push(path);
+            } else if is_todo_file(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths
+}
+
+/// Name of a todo file as shown in the app, its path relative to the data directory without
+/// the extension, such as `work/project`
+pub fn file_name(main_path: &Path, path: &Path) -> String {
+    let mut relative = path.strip_prefix(main_path).unwrap_or(path).to_path_buf();
+    relative.set_extension("");
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Path of the todo file with the given name, the inverse of `file_name`
+pub fn file_path(main_path: &Path, name: &str) -> PathBuf {
+    let mut path = main_path.to_path_buf();
+    path.extend(name.split('/'));
+    path.set_extension("md");
+    path
+}
+
+/// Check a file or folder name entered by the user, folders are separated by slashes
+pub fn validate_file_name(name: &str) -> Result<(), String> {
+    let valid = !name.contains('\\')
+        && name
+            .split('/')
+            .all(|part| !part.trim().is_empty() && !part.starts_with('.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid file name: \"{}\"", name))
     }
 }
 
 impl fmt::Display for FileList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut write_string = String::new();
-        for i in 0..self.titles.len() {
-            if i > 0 {
-                write_string.push('\n');
-            }
-            let title = &format!("# {}\n", self.titles.get(i).unwrap());
-            write_string.push_str(title);
-            write_string = write_string.trim().to_string();
-            write_string.push('\n');
-
-            let description = &self
-                .descriptions
-                .get(i)
-                .unwrap_or(&"".to_string())
-                .to_string();
-            if !description.is_empty() {
-                write_string.push_str(description);
-                write_string = write_string.trim().to_string();
-                write_string.push_str("\n\n");
+        let default_layout = ListLayout::default();
+        let mut lines: Vec<String> = self.preamble.clone();
+        for (i, title) in self.titles.iter().enumerate() {
+            let layout = self.layouts.get(i).unwrap_or(&default_layout);
+            // Keep the original heading unless the title was changed
+            if parse_title(&layout.heading) == Some(title.as_str()) {
+                lines.push(layout.heading.clone());
+            } else {
+                lines.push(format!("# {}", title));
             }
 
+            lines.extend(layout.before_description.iter().cloned());
+            let description = self.descriptions.get(i).unwrap_or(EMPTY_STRING);
             let note_vec = self.notes.get(i).unwrap_or(EMPTY_NOTE_VEC);
+            if !description.is_empty() {
+                lines.extend(description.split('\n').map(String::from));
+                if !note_vec.is_empty() {
+                    lines.extend(layout.after_description.iter().cloned());
+                }
+            }
+
             for note in note_vec.iter() {
-                write_string.push_str(&note.to_string());
-                write_string = write_string.trim().to_string();
-                write_string.push('\n');
+                note.write_lines(&layout.indent, &mut lines);
             }
+            lines.extend(layout.trailing.iter().cloned());
         }
 
-        write!(f, "{}", write_string)
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Serialized form of a single list of a file
+#[derive(Serialize, Deserialize)]
+struct ListData {
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    notes: Vec<Note>,
+    /// Heading and blank lines as found in the file, left out when they are the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    layout: Option<ListLayout>,
+}
+
+impl From<FileList> for Vec<ListData> {
+    fn from(list: FileList) -> Self {
+        let mut layouts = list.layouts.into_iter();
+        list.titles
+            .into_iter()
+            .zip(list.descriptions)
+            .zip(list.notes)
+            .map(|((title, description), notes)| ListData {
+                title,
+                description,
+                notes,
+                layout: layouts
+                    .next()
+                    .filter(|layout| *layout != ListLayout::default()),
+            })
+            .collect()
+    }
+}
+
+impl From<Vec<ListData>> for FileList {
+    fn from(data: Vec<ListData>) -> Self {
+        let mut list = FileList::default();
+        fn refresh(notes: &mut [Note]) {
+            for note in notes.iter_mut() {
+                note.meta = Metadata::parse(&note.content);
+                refresh(&mut note.children);
+            }
+        }
+
+        for mut data in data {
+            for note in data.notes.iter_mut() {
+                note.set_depth(0);
+            }
+            refresh(&mut data.notes);
+            list.push_list(data.title, data.description, data.notes);
+            if let Some(layout) = data.layout {
+                *list.layouts.last_mut().unwrap() = layout;
+            }
+        }
+        list
     }
 }
 
@@ -94,21 +394,87 @@ impl Hash for FileList {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NoteEnum {
     OPEN,
+    /// Started, `[/]`
+    #[serde(rename = "in_progress")]
+    PROGRESS,
+    /// Postponed or scheduled for later, `[>]`
+    DEFERRED,
+    /// Waiting on something else, `[!]`
+    BLOCKED,
     DONE,
     REJECTED,
 }
 
-#[derive(Clone, Debug)]
+impl NoteEnum {
+    pub const ALL: [NoteEnum; 6] = [
+        NoteEnum::OPEN,
+        NoteEnum::PROGRESS,
+        NoteEnum::DEFERRED,
+        NoteEnum::BLOCKED,
+        NoteEnum::DONE,
+        NoteEnum::REJECTED,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoteEnum::OPEN => "open",
+            NoteEnum::PROGRESS => "in progress",
+            NoteEnum::DEFERRED => "deferred",
+            NoteEnum::BLOCKED => "blocked",
+            NoteEnum::DONE => "done",
+            NoteEnum::REJECTED => "rejected",
+        }
+    }
+
+    /// Whether nothing is left to do, done and rejected notes are closed
+    pub fn is_closed(&self) -> bool {
+        matches!(self, NoteEnum::DONE | NoteEnum::REJECTED)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Note {
     pub content: String,
     pub state: NoteEnum,
+    /// Line the note was parsed from, written back as long as the note is unchanged
+    #[serde(skip)]
+    pub source: Option<String>,
+    /// Lines following the note which are not notes themselves
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<String>,
+    /// Subtasks indented below the note
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Note>,
+    /// Nesting level, 0 for notes placed directly below a heading
+    #[serde(skip)]
+    pub depth: usize,
+    /// Whether the children are hidden in the notes pane
+    #[serde(skip)]
+    pub collapsed: bool,
+    /// Due date, priority and tags found in the content
+    #[serde(flatten)]
+    pub meta: Metadata,
 }
 
 impl Note {
+    pub fn new(content: String, state: NoteEnum) -> Self {
+        Note {
+            meta: Metadata::parse(&content),
+            content,
+            state,
+            source: None,
+            extra: vec![],
+            children: vec![],
+            depth: 0,
+            collapsed: false,
+        }
+    }
     pub fn set_content(&mut self, content: String) -> &mut Self {
+        self.meta = Metadata::parse(&content);
         self.content = content;
         self
     }
@@ -116,26 +482,375 @@ impl Note {
         self.state = state;
         self
     }
-    pub fn to_string_custom(&self, start: &str) -> String {
+    pub fn set_depth(&mut self, depth: usize) -> &mut Self {
+        if self.depth != depth {
+            // The original indentation no longer applies
+            self.source = None;
+        }
+        self.depth = depth;
+        for child in self.children.iter_mut() {
+            child.set_depth(depth + 1);
+        }
+        self
+    }
+    /// Apply a closing state to all subtasks which are not closed yet
+    pub fn close_children(&mut self, state: &NoteEnum) {
+        for child in self.children.iter_mut() {
+            if !child.state.is_closed() {
+                child.set_state(state.clone());
+            }
+            child.close_children(state);
+        }
+    }
+    /// Derive the state of a parent note from its direct children. A parent with subtasks
+    /// left to do is in progress as soon as one of them is, otherwise it keeps its own state
+    /// unless it was closed.
+    pub fn sync_with_children(&mut self) {
+        if self.children.is_empty() {
+            return;
+        }
+        let has = |state: NoteEnum| self.children.iter().any(|child| child.state == state);
+        let open = self.children.iter().any(|child| !child.state.is_closed());
+        let progress = has(NoteEnum::PROGRESS);
+        let done = has(NoteEnum::DONE);
+        if progress {
+            self.state = NoteEnum::PROGRESS;
+        } else if open {
+            if self.state.is_closed() {
+                self.state = NoteEnum::OPEN;
+            }
+        } else if done {
+            self.state = NoteEnum::DONE;
+        } else {
+            self.state = NoteEnum::REJECTED;
+        }
+    }
+    /// Number of done and total direct children
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self
+            .children
+            .iter()
+            .filter(|child| child.state == NoteEnum::DONE)
+            .count();
+        (done, self.children.len())
+    }
+    pub fn checkbox(&self) -> &'static str {
         match self.state {
-            NoteEnum::DONE => format!("{} [x] {}", start, self.content),
-            NoteEnum::OPEN => format!("{} [ ] {}", start, self.content),
-            NoteEnum::REJECTED => format!("{} [-] {}", start, self.content),
+            NoteEnum::DONE => "[x]",
+            NoteEnum::OPEN => "[ ]",
+            NoteEnum::PROGRESS => "[/]",
+            NoteEnum::DEFERRED => "[>]",
+            NoteEnum::BLOCKED => "[!]",
+            NoteEnum::REJECTED => "[-]",
+        }
+    }
+    pub fn to_string_custom(&self, start: &str) -> String {
+        format!("{} {} {}", start, self.checkbox(), self.content)
+    }
+    /// Line written to file, either the original line or a freshly formatted one. A changed
+    /// note keeps the indentation of its original line, a note without one is indented by
+    /// `indent` per level.
+    pub fn to_markdown(&self, indent: &str) -> String {
+        if let Some(source) = &self.source {
+            if let Some(parsed) = parse_note(source) {
+                if parsed.content == self.content && parsed.state == self.state {
+                    return source.clone();
+                }
+            }
+            return format!("{}{}", leading_whitespace(source), self);
+        }
+        format!("{}{}", indent.repeat(self.depth), self)
+    }
+    /// Markdown lines of the note, its extra lines and subtasks
+    pub fn write_lines(&self, indent: &str, lines: &mut Vec<String>) {
+        lines.push(self.to_markdown(indent));
+        lines.extend(self.extra.iter().cloned());
+        for child in self.children.iter() {
+            child.write_lines(indent, lines);
         }
     }
 }
 
 impl fmt::Display for Note {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self.state {
-                NoteEnum::DONE => format!("- [x] {}", self.content),
-                NoteEnum::OPEN => format!("- [ ] {}", self.content),
-                NoteEnum::REJECTED => format!("- [-] {}", self.content),
+        write!(f, "{}", self.to_string_custom("-"))
+    }
+}
+
+/// Sort notes and the subtasks of each note by their metadata, keeping the order of equal notes
+pub fn sort_notes(notes: &mut [Note], key: SortKey) {
+    notes.sort_by(|a, b| compare(&a.meta, &b.meta, key));
+    for note in notes.iter_mut() {
+        sort_notes(&mut note.children, key);
+    }
+}
+
+/// Paths to every note not hidden by a collapsed parent, in display order.
+/// A path holds the index of the note on each level of the tree.
+pub fn visible_notes(notes: &[Note]) -> Vec<Vec<usize>> {
+    fn walk(notes: &[Note], prefix: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        for (i, note) in notes.iter().enumerate() {
+            prefix.push(i);
+            paths.push(prefix.clone());
+            if !note.collapsed {
+                walk(&note.children, prefix, paths);
+            }
+            prefix.pop();
+        }
+    }
+    let mut paths = Vec::new();
+    walk(notes, &mut Vec::new(), &mut paths);
+    paths
+}
+
+/// Paths to every note accepted by the filter and to their parents, ignoring collapsed notes
+pub fn filter_notes(notes: &[Note], filter: &dyn Fn(&Note) -> bool) -> Vec<Vec<usize>> {
+    fn walk(
+        notes: &[Note],
+        filter: &dyn Fn(&Note) -> bool,
+        prefix: &mut Vec<usize>,
+        paths: &mut Vec<Vec<usize>>,
+    ) {
+        for (i, note) in notes.iter().enumerate() {
+            prefix.push(i);
+            let position = paths.len();
+            walk(&note.children, filter, prefix, paths);
+            if filter(note) || paths.len() > position {
+                paths.insert(position, prefix.clone());
+            }
+            prefix.pop();
+        }
+    }
+    let mut paths = Vec::new();
+    walk(notes, filter, &mut Vec::new(), &mut paths);
+    paths
+}
+
+/// Add a completion date to the notes which became done and remove it from those which are
+/// no longer done. Both trees have to hold the same notes.
+pub fn stamp_completions(before: &[Note], after: &mut [Note], today: NaiveDate) {
+    for (old, note) in before.iter().zip(after.iter_mut()) {
+        if old.state != note.state {
+            if note.state == NoteEnum::DONE {
+                note.set_content(with_completed(&note.content, Some(today)));
+            } else if old.state == NoteEnum::DONE && note.meta.completed.is_some() {
+                note.set_content(with_completed(&note.content, None));
+            }
+        }
+        stamp_completions(&old.children, &mut note.children, today);
+    }
+}
+
+/// Insert the next occurrence below every recurring note which became done, subtasks
+/// included, and return where they were inserted. Subtasks of a note which recurs itself
+/// come back with its copy. Both trees have to hold the same notes.
+pub fn repeat_completed(
+    before: &[Note],
+    notes: &mut Vec<Note>,
+    today: NaiveDate,
+) -> Vec<Vec<usize>> {
+    fn completed(
+        before: &[Note],
+        notes: &[Note],
+        path: &mut Vec<usize>,
+        found: &mut Vec<Vec<usize>>,
+    ) {
+        for (i, (old, note)) in before.iter().zip(notes.iter()).enumerate() {
+            path.push(i);
+            if note.meta.recurrence.is_some()
+                && note.state == NoteEnum::DONE
+                && old.state != NoteEnum::DONE
+            {
+                found.push(path.clone());
+            } else {
+                completed(&old.children, &note.children, path, found);
             }
-        )
+            path.pop();
+        }
+    }
+    let mut found = Vec::new();
+    completed(before, notes, &mut Vec::new(), &mut found);
+    // From the last, so that the paths before stay valid
+    let mut inserted = Vec::new();
+    for path in found.into_iter().rev() {
+        if let Some(next) = next_occurrence(note_at(notes, &path).unwrap(), today) {
+            let mut next_path = path;
+            *next_path.last_mut().unwrap() += 1;
+            insert_note(notes, &next_path, next);
+            inserted.push(next_path);
+        }
+    }
+    inserted
+}
+
+/// Open copy of a recurring note with the due date of its next occurrence, subtasks are
+/// reopened as well
+pub fn next_occurrence(note: &Note, today: NaiveDate) -> Option<Note> {
+    fn reopen(note: &mut Note) {
+        note.state = NoteEnum::OPEN;
+        note.source = None;
+        note.set_content(with_completed(&note.content, None));
+        note.children.iter_mut().for_each(reopen);
+    }
+    let recurrence = note.meta.recurrence?;
+    let mut next = note.clone();
+    reopen(&mut next);
+    next.set_content(with_due(
+        &next.content,
+        recurrence.next(note.meta.due, today),
+    ));
+    next.collapsed = false;
+    Some(next)
+}
+
+/// Date of a journal file, which is named like `2026-10-18` and placed in the data directory
+pub fn journal_date(name: &str) -> Option<NaiveDate> {
+    let is_date = name.len() == 10
+        && name.char_indices().all(|(i, c)| match i {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        });
+    if is_date {
+        NaiveDate::parse_from_str(name, DATE_FORMAT).ok()
+    } else {
+        None
+    }
+}
+
+/// Copy the open top level notes of a journal day to another list, the day itself is left
+/// as it is
+pub fn carry_over(from: &FileList, into: &mut Vec<Note>) {
+    into.extend(
+        from.notes
+            .iter()
+            .flatten()
+            .filter(|note| note.state == NoteEnum::OPEN)
+            .cloned(),
+    );
+}
+
+/// Number of notes with the given state, subtasks included
+pub fn count_notes(notes: &[Note], state: &NoteEnum) -> usize {
+    notes
+        .iter()
+        .map(|note| (note.state == *state) as usize + count_notes(&note.children, state))
+        .sum()
+}
+
+pub fn note_at<'a>(notes: &'a [Note], path: &[usize]) -> Option<&'a Note> {
+    let (first, rest) = path.split_first()?;
+    let note = notes.get(*first)?;
+    if rest.is_empty() {
+        Some(note)
+    } else {
+        note_at(&note.children, rest)
+    }
+}
+
+pub fn note_at_mut<'a>(notes: &'a mut [Note], path: &[usize]) -> Option<&'a mut Note> {
+    let (first, rest) = path.split_first()?;
+    let note = notes.get_mut(*first)?;
+    if rest.is_empty() {
+        Some(note)
+    } else {
+        note_at_mut(&mut note.children, rest)
+    }
+}
+
+fn siblings_mut<'a>(notes: &'a mut Vec<Note>, path: &[usize]) -> Option<&'a mut Vec<Note>> {
+    match path.split_last() {
+        Some((_, [])) => Some(notes),
+        Some((_, parent)) => note_at_mut(notes, parent).map(|note| &mut note.children),
+        None => None,
+    }
+}
+
+/// Insert a note at a path, moving the note there and its following siblings down
+pub fn insert_note(notes: &mut Vec<Note>, path: &[usize], mut note: Note) -> Option<()> {
+    let index = *path.last()?;
+    let siblings = siblings_mut(notes, path)?;
+    if index > siblings.len() {
+        return None;
+    }
+    note.set_depth(path.len() - 1);
+    siblings.insert(index, note);
+    update_parents(notes, path);
+    Some(())
+}
+
+/// Remove a note together with its subtasks
+pub fn remove_note(notes: &mut Vec<Note>, path: &[usize]) -> Option<Note> {
+    let index = *path.last()?;
+    let siblings = siblings_mut(notes, path)?;
+    if index < siblings.len() {
+        let note = siblings.remove(index);
+        update_parents(notes, path);
+        Some(note)
+    } else {
+        None
+    }
+}
+
+/// Make a note the last subtask of its previous sibling, returns the new path
+pub fn indent_note(notes: &mut Vec<Note>, path: &[usize]) -> Option<Vec<usize>> {
+    let index = *path.last()?;
+    if index == 0 {
+        return None;
+    }
+    let siblings = siblings_mut(notes, path)?;
+    let mut note = siblings.remove(index);
+    let parent = siblings.get_mut(index - 1).unwrap();
+    note.set_depth(parent.depth + 1);
+    parent.collapsed = false;
+    parent.children.push(note);
+
+    let mut new_path = path.to_vec();
+    *new_path.last_mut().unwrap() = index - 1;
+    new_path.push(parent.children.len() - 1);
+    update_parents(notes, &new_path);
+    Some(new_path)
+}
+
+/// Move a note out of its parent right below it, returns the new path
+pub fn outdent_note(notes: &mut Vec<Note>, path: &[usize]) -> Option<Vec<usize>> {
+    if path.len() < 2 {
+        return None;
+    }
+    let parent_path = &path[..path.len() - 1];
+    let old_parent = note_at_mut(notes, parent_path)?;
+    let mut note = old_parent.children.remove(*path.last().unwrap());
+    note.set_depth(old_parent.depth);
+    old_parent.sync_with_children();
+
+    let mut new_path = parent_path.to_vec();
+    *new_path.last_mut().unwrap() += 1;
+    let siblings = siblings_mut(notes, &new_path)?;
+    siblings.insert(*new_path.last().unwrap(), note);
+    update_parents(notes, &new_path);
+    Some(new_path)
+}
+
+/// Swap a note with its previous or next sibling, returns the new path
+pub fn move_note(notes: &mut Vec<Note>, path: &[usize], up: bool) -> Option<Vec<usize>> {
+    let index = *path.last()?;
+    let siblings = siblings_mut(notes, path)?;
+    let target = if up { index.checked_sub(1)? } else { index + 1 };
+    if target >= siblings.len() {
+        return None;
+    }
+    siblings.swap(index, target);
+
+    let mut new_path = path.to_vec();
+    *new_path.last_mut().unwrap() = target;
+    Some(new_path)
+}
+
+/// Recalculate the state of every parent of the note at the given path
+pub fn update_parents(notes: &mut [Note], path: &[usize]) {
+    for depth in (1..path.len()).rev() {
+        if let Some(parent) = note_at_mut(notes, &path[..depth]) {
+            parent.sync_with_children();
+        }
     }
 }
 
@@ -149,105 +864,573 @@ pub fn extract_filename(path: &Path) -> String {
     }
 }
 
-pub fn extract_naked_filename(path: &Path) -> String {
-    let mut working_path = path.to_path_buf();
-    match path.file_name() {
-        Some(_value) => {
-            working_path.set_extension("");
-            match working_path.file_name() {
-                Some(value) => match value.to_os_string().into_string() {
-                    Ok(value) => value,
-                    _ => exit(1),
-                },
-                _ => exit(1),
-            }
+// Read lines from file, a trailing newline results in a trailing empty line
+pub fn lines_from_file(filename: impl AsRef<Path>) -> Result<Vec<String>, String> {
+    let path = filename.as_ref();
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|file| BufReader::new(file).read_to_string(&mut content))
+        .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+    Ok(content.split('\n').map(String::from).collect())
+}
+
+pub fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+pub fn parse_title(line: &str) -> Option<&str> {
+    line.strip_prefix("# ").map(|title| title.trim())
+}
+
+pub fn parse_note(line: &str) -> Option<Note> {
+    let rest = line.trim_start_matches([' ', '\t']).strip_prefix("- ")?;
+    let (state, rest) = if let Some(rest) = rest.strip_prefix("[]") {
+        (NoteEnum::OPEN, rest)
+    } else {
+        let marker = rest.strip_prefix('[')?.chars().next()?;
+        let state = match marker {
+            'x' | 'X' => NoteEnum::DONE,
+            ' ' => NoteEnum::OPEN,
+            '/' => NoteEnum::PROGRESS,
+            '>' => NoteEnum::DEFERRED,
+            '!' => NoteEnum::BLOCKED,
+            '-' => NoteEnum::REJECTED,
+            _ => return None,
+        };
+        (state, rest[1 + marker.len_utf8()..].strip_prefix(']')?)
+    };
+    let content = rest
+        .strip_prefix(' ')
+        .unwrap_or(rest)
+        .trim_end_matches('\r');
+
+    let mut note = Note::new(content.to_string(), state);
+    note.source = Some(line.to_string());
+    Some(note)
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Whitespace of one level of subtasks, taken from the first note indented further than the
+/// note before it
+fn detect_indent(notes: &[(usize, Note)]) -> Option<String> {
+    notes.windows(2).find_map(|pair| {
+        let (parent_width, parent) = &pair[0];
+        let (width, child) = &pair[1];
+        if width <= parent_width {
+            return None;
         }
-        _ => exit(1),
+        let parent = leading_whitespace(parent.source.as_deref()?);
+        let child = leading_whitespace(child.source.as_deref()?);
+        Some(child.strip_prefix(parent).unwrap_or(child).to_string())
+    })
+}
+
+// Width of the indentation of a line, counting tabs as four spaces
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+// Nest notes which are indented further than the note preceding them
+fn build_tree(
+    notes: &mut std::iter::Peekable<std::vec::IntoIter<(usize, Note)>>,
+    parent_indentation: Option<usize>,
+    depth: usize,
+) -> Vec<Note> {
+    let mut tree = Vec::new();
+    while let Some((width, _)) = notes.peek() {
+        if parent_indentation.is_some_and(|parent| *width <= parent) {
+            break;
+        }
+        let (width, mut note) = notes.next().unwrap();
+        note.depth = depth;
+        note.children = build_tree(notes, Some(width), depth + 1);
+        tree.push(note);
     }
+    tree
+}
+
+/// Text without its leading and trailing blank lines
+pub fn trim_blank_lines(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let start = lines.iter().take_while(|line| is_blank(line)).count();
+    let end = lines.len()
+        - lines[start..]
+            .iter()
+            .rev()
+            .take_while(|line| is_blank(line))
+            .count();
+    lines[start..end].join("\n")
+}
+
+// Split trailing blank lines off the end of a block
+fn split_trailing_blanks(lines: &mut Vec<String>) -> Vec<String> {
+    let count = lines.iter().rev().take_while(|line| is_blank(line)).count();
+    lines.split_off(lines.len() - count)
 }
 
-// Read lines from file
-pub fn lines_from_file(filename: impl AsRef<Path>) -> Vec<String> {
-    let file = File::open(filename).expect("no such file");
-    let buf = BufReader::new(file);
-    buf.lines()
-        .map(|l| l.expect("Could not parse line"))
-        .collect()
+fn finish_list(
+    list: &mut FileList,
+    heading: String,
+    mut description: Vec<String>,
+    mut notes: Vec<(usize, Note)>,
+) {
+    // Blank lines of a list without content separate it from the next one
+    let count = if notes.is_empty() && description.iter().all(|line| is_blank(line)) {
+        0
+    } else {
+        description.iter().take_while(|line| is_blank(line)).count()
+    };
+    let before_description: Vec<String> = description.drain(..count).collect();
+
+    let indent = detect_indent(&notes).unwrap_or_default();
+    let (after_description, trailing) = match notes.last_mut() {
+        Some((_, note)) => (
+            split_trailing_blanks(&mut description),
+            split_trailing_blanks(&mut note.extra),
+        ),
+        None => (vec![], split_trailing_blanks(&mut description)),
+    };
+
+    list.titles
+        .push(parse_title(&heading).unwrap_or_default().to_string());
+    list.descriptions.push(description.join("\n"));
+    list.notes
+        .push(build_tree(&mut notes.into_iter().peekable(), None, 0));
+    list.layouts.push(ListLayout {
+        heading,
+        before_description,
+        after_description,
+        trailing,
+        indent,
+    });
 }
 
 pub fn parse_lines(lines: Vec<String>) -> FileList {
-    let mut titles: Vec<String> = Vec::new();
-    let mut descriptions: Vec<String> = Vec::new();
-    let mut notes: Vec<Vec<Note>> = Vec::new();
+    let mut list = FileList::default();
 
     // Working data designed to be consumed
-    let mut working_title: &String = &String::new();
-    let mut working_description: String = String::new();
-    let mut working_notes: Vec<Note> = Vec::new();
+    let mut working_heading: Option<String> = None;
+    let mut working_description: Vec<String> = Vec::new();
+    let mut working_notes: Vec<(usize, Note)> = Vec::new();
+    let mut in_fence = false;
 
     for line in lines {
-        if line.starts_with("# ") {
-            if !working_title.is_empty() {
-                descriptions.push((&working_description).to_string());
-                notes.push(working_notes)
-            }
-            let title = line.split("# ").collect();
-            titles.push(title);
-            working_title = titles.last().unwrap();
-            working_description = String::new();
-            working_notes = Vec::new();
-        } else if line.starts_with("- ") {
-            if !working_title.is_empty() {
-                if line.starts_with("- [x]") {
-                    let content = line.split("- [x] ").collect();
-                    working_notes.push(Note {
-                        content,
-                        state: NoteEnum::DONE,
-                    });
-                } else if line.starts_with("- [ ]") {
-                    let content = line.split("- [ ] ").collect();
-                    working_notes.push(Note {
-                        content,
-                        state: NoteEnum::OPEN,
-                    });
-                } else if line.starts_with("- []") {
-                    let content = line.split("- [] ").collect();
-                    working_notes.push(Note {
-                        content,
-                        state: NoteEnum::OPEN,
-                    });
-                } else if line.starts_with("- [-]") {
-                    let content = line.split("- [-] ").collect();
-                    working_notes.push(Note {
-                        content,
-                        state: NoteEnum::REJECTED,
-                    });
-                }
+        if !in_fence && parse_title(&line).is_some() {
+            if let Some(heading) = working_heading.take() {
+                finish_list(&mut list, heading, working_description, working_notes);
             }
-        } else if !working_title.is_empty() {
-            // Add space on new line
-            if !working_description.is_empty() {
-                working_description.push(' ')
+            working_heading = Some(line);
+            working_description = Vec::new();
+            working_notes = Vec::new();
+            continue;
+        }
+
+        if working_heading.is_some() && !in_fence {
+            if let Some(note) = parse_note(&line) {
+                working_notes.push((indentation(&line), note));
+                continue;
             }
-            working_description.push_str(&line);
+        }
+
+        // Anything else is kept as is
+        if is_fence(&line) {
+            in_fence = !in_fence;
+        }
+        if working_heading.is_none() {
+            list.preamble.push(line);
+        } else if let Some((_, note)) = working_notes.last_mut() {
+            note.extra.push(line);
+        } else {
+            working_description.push(line);
         }
     }
 
     // Add last
-    // if !descriptions.is_empty() || descriptions.len() + 1 == titles.len() {
-    //     descriptions.push(String::new());
-    // } else {
-    descriptions.push(working_description);
-    // }
-    // if !notes.is_empty() || descriptions.len() + 1 == titles.len() {
-    //     notes.push(Vec::new());
-    // } else {
-    notes.push(working_notes);
-    // }
-
-    FileList {
-        titles,
-        descriptions,
-        notes,
+    if let Some(heading) = working_heading {
+        finish_list(&mut list, heading, working_description, working_notes);
+    }
+
+    // Lists without subtasks are indented like the rest of the file
+    let indent = list
+        .layouts
+        .iter()
+        .map(|layout| layout.indent.clone())
+        .find(|indent| !indent.is_empty())
+        .unwrap_or_else(|| DEFAULT_INDENT.to_string());
+    for layout in list.layouts.iter_mut() {
+        if layout.indent.is_empty() {
+            layout.indent = indent.clone();
+        }
+    }
+
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::{
+        apply_order, backup_path, carry_over, count_notes, file_name, file_path, indent_note,
+        insert_note, is_todo_file, journal_date, lines_from_file, move_note, next_occurrence,
+        outdent_note, parse_lines, repeat_completed, stamp_completions, temp_path, todo_files,
+        trim_blank_lines, update_parents, validate_file_name, visible_notes, write_order, FileList,
+        Note, NoteEnum, STARTER_FILE_CONTENT,
+    };
+    use std::fs;
+
+    fn lines(content: &str) -> Vec<String> {
+        content.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let content = "Some preamble\n\n# Title \n\nFirst paragraph\nstill first\n\n## Sub\n\n\
+            - [x] done\n  continuation\n* bullet\n- []  open\n\n```sh\n# not a heading\n- [ ] not a note\n```\n\n\
+            # Second\n[link](https://example.com)\n- [-] rejected\r\n\n\n";
+        let list = parse_lines(lines(content));
+        assert_eq!(list.titles, vec!["Title", "Second"]);
+        assert_eq!(
+            list.descriptions[0],
+            "First paragraph\nstill first\n\n## Sub"
+        );
+        assert_eq!(list.notes[0].len(), 2);
+        assert_eq!(list.notes[1][0].content, "rejected");
+        assert_eq!(list.to_string(), content);
+        assert_eq!(trim_blank_lines("\n \nline\n\nline\n\n"), "line\n\nline");
+
+        let tutorial = parse_lines(lines(STARTER_FILE_CONTENT));
+        assert_eq!(tutorial.to_string(), STARTER_FILE_CONTENT);
+    }
+
+    #[test]
+    fn edit_test() {
+        let content =
+            "# Todo\n\n* [link](https://example.com)\n\n- []  first\n- [ ] second\n  details\n";
+        let mut list = parse_lines(lines(content));
+        list.notes[0][1].set_state(NoteEnum::DONE);
+        list.notes[0].push(Note::new("third".to_string(), NoteEnum::OPEN));
+        assert_eq!(
+            list.to_string(),
+            "# Todo\n\n* [link](https://example.com)\n\n- []  first\n- [x] second\n  details\n- [ ] third\n"
+        );
+
+        list.titles[0] = "Done".to_string();
+        list.push_list("New".to_string(), "Description".to_string(), vec![]);
+        assert!(list.to_string().starts_with("# Done\n\n* [link]"));
+        assert!(list
+            .to_string()
+            .ends_with("- [ ] third\n\n# New\nDescription\n"));
+    }
+
+    #[test]
+    fn nesting_test() {
+        let content = "# Todo\n- [ ] parent\n    - [x] child\n        - [ ] grandchild\n    - [-] second child\n\
+            \t- [ ] third child\n- [ ] sibling\n";
+        let mut list = parse_lines(lines(content));
+        let notes = &mut list.notes[0];
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].children.len(), 3);
+        assert_eq!(notes[0].children[0].children[0].depth, 2);
+        assert_eq!(visible_notes(notes).len(), 6);
+        assert_eq!(count_notes(notes, &NoteEnum::OPEN), 4);
+        assert_eq!(list.to_string(), content);
+
+        let notes = &mut list.notes[0];
+        notes[0].children[2].set_state(NoteEnum::DONE);
+        notes[0].children[0].children[0].set_state(NoteEnum::DONE);
+        update_parents(notes, &[0, 2]);
+        assert_eq!(notes[0].state, NoteEnum::DONE);
+
+        assert_eq!(indent_note(notes, &[1]), Some(vec![0, 3]));
+        assert_eq!(notes[0].state, NoteEnum::OPEN);
+        assert_eq!(outdent_note(notes, &[0, 0, 0]), Some(vec![0, 1]));
+        let written = list.to_string();
+        assert_eq!(
+            written,
+            "# Todo\n- [ ] parent\n    - [x] child\n    - [x] grandchild\n    - [-] second child\n\
+            \t- [x] third child\n    - [ ] sibling\n"
+        );
+        assert_eq!(
+            tree(&parse_lines(lines(&written)).notes[0]),
+            tree(&list.notes[0])
+        );
+
+        // Changed notes keep the indentation of their line
+        let mut list = parse_lines(lines("# T\n- [ ] parent\n    - [ ] a\n    - [ ] b\n"));
+        list.notes[0][0].children[0].set_state(NoteEnum::DONE);
+        let written = list.to_string();
+        assert_eq!(written, "# T\n- [ ] parent\n    - [x] a\n    - [ ] b\n");
+        assert_eq!(
+            tree(&parse_lines(lines(&written)).notes[0]),
+            tree(&list.notes[0])
+        );
+
+        // Lists without subtasks take the indentation of the file
+        let mut list = parse_lines(lines("# A\n- [ ] a\n\t- [ ] b\n# B\n- [ ] c\n- [ ] d\n"));
+        assert_eq!(indent_note(&mut list.notes[1], &[1]), Some(vec![0, 0]));
+        assert_eq!(
+            list.to_string(),
+            "# A\n- [ ] a\n\t- [ ] b\n# B\n- [ ] c\n\t- [ ] d\n"
+        );
+    }
+
+    /// Depth, content and state of every note in order, to compare trees
+    fn tree(notes: &[Note]) -> Vec<(usize, String, NoteEnum)> {
+        let mut flat = Vec::new();
+        for note in notes {
+            flat.push((note.depth, note.content.clone(), note.state.clone()));
+            flat.extend(tree(&note.children));
+        }
+        flat
+    }
+
+    #[test]
+    fn state_test() {
+        let content =
+            "# Todo\n- [/] started\n- [>] later\n- [!] waiting\n- [X] done\n- [?] unknown\n";
+        let mut list = parse_lines(lines(content));
+        let states: Vec<NoteEnum> = list.notes[0]
+            .iter()
+            .map(|note| note.state.clone())
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                NoteEnum::PROGRESS,
+                NoteEnum::DEFERRED,
+                NoteEnum::BLOCKED,
+                NoteEnum::DONE
+            ]
+        );
+        assert_eq!(list.to_string(), content);
+
+        let notes = &mut list.notes[0];
+        assert_eq!(indent_note(notes, &[1]), Some(vec![0, 0]));
+        assert_eq!(notes[0].state, NoteEnum::PROGRESS);
+        notes[0].set_state(NoteEnum::BLOCKED);
+        update_parents(notes, &[0, 0]);
+        assert_eq!(notes[0].state, NoteEnum::BLOCKED);
+        notes[0].close_children(&NoteEnum::DONE);
+        assert_eq!(notes[0].children[0].state, NoteEnum::DONE);
+    }
+
+    #[test]
+    fn stamp_completions_test() {
+        let mut list = parse_lines(lines(
+            "# Todo
+- [/] started
+  - [>] later
+",
+        ));
+        let notes = &mut list.notes[0];
+        let before = notes.clone();
+        notes[0].close_children(&NoteEnum::DONE);
+        let today = chrono::NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        stamp_completions(&before, notes, today);
+        assert_eq!(notes[0].children[0].content, "later done:2026-10-18");
+        assert_eq!(notes[0].content, "started");
+        let before = notes.clone();
+        notes[0].children[0].set_state(NoteEnum::OPEN);
+        stamp_completions(&before, notes, today);
+        assert_eq!(notes[0].children[0].content, "later");
+    }
+
+    #[test]
+    fn next_occurrence_test() {
+        let today = chrono::NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let mut review = parse_lines(lines(
+            "# Todo\n- [x] review every:week due:2026-10-16 done:2026-10-18\n  - [x] inbox\n",
+        ));
+        let notes = &mut review.notes[0];
+        let next = next_occurrence(&notes[0], today).unwrap();
+        insert_note(notes, &[1], next);
+        assert_eq!(
+            review.to_string(),
+            "# Todo\n- [x] review every:week due:2026-10-16 done:2026-10-18\n  - [x] inbox\n\
+            - [ ] review every:week due:2026-10-23\n  - [ ] inbox\n"
+        );
+
+        // Recurring subtasks closed along with their parent repeat as well, which reopens the
+        // parent, unless the parent repeats and brings them back itself
+        let mut list = parse_lines(lines(
+            "# Todo\n- [ ] chores\n  - [ ] water every:week due:2026-10-16\n  - [ ] dust\n- [ ] pay every:month due:2026-10-18\n  - [ ] rent every:month due:2026-10-18\n",
+        ));
+        let notes = &mut list.notes[0];
+        let before = notes.clone();
+        for note in notes.iter_mut() {
+            note.close_children(&NoteEnum::DONE);
+            note.set_state(NoteEnum::DONE);
+        }
+        assert_eq!(
+            repeat_completed(&before, notes, today),
+            vec![vec![2], vec![0, 1]]
+        );
+        assert_eq!(
+            list.to_string(),
+            "# Todo\n- [ ] chores\n  - [x] water every:week due:2026-10-16\n  - [ ] water every:week due:2026-10-23\n  - [x] dust\n\
+            - [x] pay every:month due:2026-10-18\n  - [x] rent every:month due:2026-10-18\n\
+            - [ ] pay every:month due:2026-11-18\n  - [ ] rent every:month due:2026-10-18\n"
+        );
+    }
+
+    #[test]
+    fn journal_test() {
+        assert_eq!(
+            journal_date("2026-10-18"),
+            chrono::NaiveDate::from_ymd_opt(2026, 10, 18)
+        );
+        assert_eq!(journal_date("2026-13-01"), None);
+        assert_eq!(journal_date("2026-1-018"), None);
+        assert_eq!(journal_date("work/2026-10-18"), None);
+
+        let content = "# Tasks\n- [ ] call bob\n  - [x] find number\n- [x] done\n- [/] report\n# Notes\n- [!] waiting\n- [ ] read\n";
+        let yesterday = parse_lines(lines(content));
+        let mut today = vec![];
+        carry_over(&yesterday, &mut today);
+        let contents: Vec<&str> = today.iter().map(|note| note.content.as_str()).collect();
+        assert_eq!(contents, vec!["call bob", "read"]);
+        assert_eq!(today[0].children.len(), 1);
+        assert_eq!(yesterday.to_string(), content);
+    }
+
+    #[test]
+    fn json_test() {
+        let content = "# Todo\nText\n- [x] parent\n  - [-] child\n";
+        let list = parse_lines(lines(content));
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(
+            json,
+            r##"[{"title":"Todo","description":"Text","notes":[{"content":"parent","state":"done","children":[{"content":"child","state":"rejected"}]}],"layout":{"heading":"# Todo","before_description":[],"after_description":[],"trailing":[""],"indent":"  "}}]"##
+        );
+        let list: FileList = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.notes[0][0].children[0].depth, 1);
+        assert_eq!(list.to_string(), content);
+
+        // Lists without a layout are written like new ones
+        let list: FileList =
+            serde_json::from_str(r#"[{"title":"Todo","notes":[{"content":"a","state":"open"}]}]"#)
+                .unwrap();
+        assert_eq!(list.to_string(), "# Todo\n- [ ] a\n");
+    }
+
+    #[test]
+    fn write_test() {
+        let dir = std::env::temp_dir().join(format!("yoku-write-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todo.md");
+
+        parse_lines(lines("# First\n")).write(&path).unwrap();
+        parse_lines(lines("# Second\n")).write(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Second\n");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "# First\n");
+        assert!(!temp_path(&path).exists());
+        assert!(is_todo_file(&path));
+        assert!(!is_todo_file(&backup_path(&path)));
+
+        // A directory in place of the file makes the rename fail
+        let blocked = dir.join("blocked.md");
+        fs::create_dir_all(blocked.join("inner")).unwrap();
+        assert!(parse_lines(lines("# Todo\n")).write(&blocked).is_err());
+        assert!(!temp_path(&blocked).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lines_from_file_test() {
+        let dir = std::env::temp_dir().join(format!("yoku-read-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todo.md");
+        fs::write(&path, "# Todo\n").unwrap();
+        assert_eq!(
+            lines_from_file(&path),
+            Ok(vec!["# Todo".to_string(), String::new()])
+        );
+        fs::write(&path, [b'#', b' ', 0xff, b'\n']).unwrap();
+        assert!(lines_from_file(&path).is_err());
+        assert!(lines_from_file(dir.join("missing.md")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn order_test() {
+        let mut list = parse_lines(lines(
+            "# A\n- [ ] one\n  - [ ] child\n- [ ] two\n# B\nText\n",
+        ));
+        assert_eq!(move_note(&mut list.notes[0], &[0], true), None);
+        assert_eq!(move_note(&mut list.notes[0], &[0, 0], false), None);
+        assert_eq!(move_note(&mut list.notes[0], &[0], false), Some(vec![1]));
+        list.swap(0, 1);
+        assert_eq!(
+            list.to_string(),
+            "# B\nText\n# A\n- [ ] two\n- [ ] one\n  - [ ] child\n"
+        );
+        let copy = list.extract(1);
+        list.insert_lists(1, copy);
+        assert_eq!(list.titles, vec!["B", "A", "A"]);
+        assert_eq!(list.notes[2][1].children[0].content, "child");
+
+        let dir = std::env::temp_dir().join(format!("yoku-order-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut paths = vec![dir.join("a.md"), dir.join("b.md"), dir.join("c.md")];
+        write_order(&dir, &paths).unwrap();
+        assert!(!dir.join(".order").exists());
+
+        paths.swap(0, 2);
+        write_order(&dir, &paths).unwrap();
+        let mut loaded = vec![
+            dir.join("a.md"),
+            dir.join("b.md"),
+            dir.join("c.md"),
+            dir.join("d.md"),
+        ];
+        apply_order(&dir, &mut loaded);
+        assert_eq!(
+            loaded,
+            vec![
+                dir.join("c.md"),
+                dir.join("b.md"),
+                dir.join("a.md"),
+                dir.join("d.md")
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn folder_test() {
+        let dir = std::env::temp_dir().join(format!("yoku-folder-test-{}", std::process::id()));
+        for name in [
+            "work/b/c.md",
+            "work/a.md",
+            ".git/x.md",
+            "notes.txt",
+            "z.md",
+            "z.md.bak",
+        ] {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "# Todo\n").unwrap();
+        }
+        let paths = todo_files(&dir);
+        let names: Vec<String> = paths.iter().map(|path| file_name(&dir, path)).collect();
+        assert_eq!(names, vec!["work/a", "work/b/c", "z"]);
+        assert_eq!(file_path(&dir, "work/b/c"), paths[1]);
+
+        assert!(validate_file_name("work/project").is_ok());
+        for name in ["", "work/", "../up", "work/.hidden", "a\\b"] {
+            assert!(validate_file_name(name).is_err());
+        }
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,129 +1,345 @@
 pub mod app;
+pub mod editor;
+pub mod external;
+pub mod help;
+pub mod history;
+pub mod keymap;
+pub mod markdown;
+pub mod register;
+pub mod search;
+pub mod selection;
+pub mod theme;
+pub mod watch;
 
-use crate::todo::NoteEnum;
-use crate::ui::app::{App, EditorMode, EMPTY_LIST, EMPTY_NOTE_VEC, EMPTY_STRING};
-use crossterm::event::{self};
-use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crate::meta::{parse_token, relative_date, Priority, SortKey, Token};
+use crate::todo::{count_notes, journal_date, note_at, NoteEnum};
+use crate::ui::app::{App, Confirm, EditorMode, EMPTY_LIST};
+use crate::ui::help::help_entries;
+use crate::ui::keymap::Action;
+use crate::ui::markdown::markdown_lines;
+use crate::ui::theme::Theme;
+use crate::ui::watch::{Resolution, POLL_INTERVAL};
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
 use std::io;
-use tui::widgets::{List, ListItem, Paragraph};
+use std::time::Instant;
+use tui::widgets::{Clear, List, ListItem, Paragraph, Wrap};
 use tui::{
     backend::Backend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
     text::{Span, Spans},
     widgets::{Block, Borders, Tabs},
     Frame, Terminal,
 };
+use unicode_width::UnicodeWidthStr;
+
+/// Rows of the input box for multi-line text, longer text scrolls
+const MAX_INPUT_LINES: usize = 6;
+/// Columns of a journal day tab: the date, its padding and the divider
+const JOURNAL_TAB_WIDTH: u16 = 13;
 
 pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+    let mut last_check = Instant::now();
     loop {
+        // Never reload while text is being entered for the current file
+        if app.mode == EditorMode::Nothing && last_check.elapsed() >= POLL_INTERVAL {
+            app.check_external_changes();
+            last_check = Instant::now();
+        }
         terminal.draw(|f| ui(f, &mut app))?;
 
+        if !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
         if let Event::Key(key) = event::read().unwrap() {
+            app.message = None;
+            if let Some(scroll) = app.help {
+                app.help = match app.config.keys.get(key) {
+                    Some(Action::Up) => Some(scroll.saturating_sub(1)),
+                    Some(Action::Down) => Some(scroll + 1),
+                    _ => None,
+                };
+                continue;
+            }
+            if is_interrupt(key) {
+                if app.request_quit() {
+                    return Ok(());
+                }
+                continue;
+            }
+            // Normal and visual mode bind help like any other action, the other modes only
+            // take keys which are not typed as text
+            let typed = matches!(key.code, KeyCode::Char(_))
+                && !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            if !matches!(app.mode, EditorMode::Nothing | EditorMode::Visual)
+                && app.config.keys.get(key) == Some(Action::Help)
+                && !(typed && app.mode.has_input())
+            {
+                app.help = Some(0);
+                continue;
+            }
             match app.mode {
-                EditorMode::Nothing => match key.code {
-                    KeyCode::Char('o') => app.create_note(),
-                    KeyCode::Char('u') => app.create_file(),
-                    KeyCode::Char('i') => app.create_list(),
-                    KeyCode::Char('r') => app.remove(),
-                    KeyCode::Char('e') => {
-                        if key.modifiers == KeyModifiers::CONTROL {
-                            app.change_description();
-                        } else {
-                            app.change()
-                        }
-                    }
-                    KeyCode::Char('q') => {
-                        if key.modifiers != KeyModifiers::CONTROL {
-                            app.save();
-                        }
-                        return Ok(());
-                    }
-                    KeyCode::Char('c') => {
-                        if key.modifiers == KeyModifiers::CONTROL {
-                            // app.save();
-                            return Ok(());
+                EditorMode::Nothing => match app.config.keys.get(key) {
+                    Some(Action::SaveAndQuit) => match app.save() {
+                        Ok(_) => return Ok(()),
+                        Err(err) => {
+                            app.message = Some(format!("{err}. Ctrl + q quits without saving"))
                         }
+                    },
+                    Some(Action::Quit) if app.request_quit() => return Ok(()),
+                    Some(Action::EditExternally) => {
+                        suspend(terminal)?;
+                        app.edit_externally();
+                        resume(terminal)?;
                     }
-                    KeyCode::Right => app.next(),
-                    KeyCode::Left => app.previous(),
-                    KeyCode::Up => app.navigate_up(),
-                    KeyCode::Down => app.navigate_down(),
-                    KeyCode::Char('w') => app.navigate_up(),
-                    KeyCode::Char('s') => app.navigate_down(),
-                    KeyCode::Char('d') => app.next(),
-                    KeyCode::Char('a') => app.previous(),
-                    KeyCode::Char('k') => app.navigate_up(),
-                    KeyCode::Char('h') => app.previous(),
-                    KeyCode::Char('j') => app.navigate_down(),
-                    KeyCode::Char('l') => app.next(),
-                    KeyCode::Esc => {
-                        if app.cursor_vertical == 2 {
-                            app.note_index = 0;
-                            app.navigate_up();
-                        }
-                    }
-                    KeyCode::Enter => {
-                        if app.cursor_vertical == 2 {
-                            app.cycle_note_state()
-                        }
+                    Some(action) => handle_normal_action(&mut app, action),
+                    None => {}
+                },
+                EditorMode::Search => match key.code {
+                    _ if !typed && app.config.dialog_keys.get(key) == Some(Action::ToggleRegex) => {
+                        app.toggle_search_regex()
                     }
-                    KeyCode::Char(' ') => {
-                        if app.cursor_vertical == 2 {
-                            app.cycle_note_state()
+                    KeyCode::Enter => app.confirm_search(),
+                    KeyCode::Esc => app.clear_search(),
+                    _ => {
+                        let before = app.input.text().to_string();
+                        if app.input.handle_key(key) && app.input.text() != before {
+                            app.update_search();
                         }
                     }
-                    KeyCode::Char('x') => {
-                        if app.cursor_vertical == 2 {
-                            app.set_note_state(NoteEnum::DONE)
-                        }
+                },
+                EditorMode::SearchResults => match app.config.dialog_keys.get(key) {
+                    Some(Action::Select) => app.jump_to_search_result(),
+                    Some(Action::Back) => app.mode = EditorMode::Nothing,
+                    _ => match app.config.keys.get(key) {
+                        Some(Action::Up) => app.next_search_result(false),
+                        Some(Action::Down) => app.next_search_result(true),
+                        Some(Action::SearchResults) => app.mode = EditorMode::Nothing,
+                        _ => {}
+                    },
+                },
+                EditorMode::Visual => {
+                    if let Some(action) = app.config.visual_keys.get(key) {
+                        handle_visual_action(&mut app, action);
                     }
-                    KeyCode::Char('+') => {
-                        if app.cursor_vertical == 2 {
-                            app.set_note_state(NoteEnum::DONE)
-                        }
+                }
+                EditorMode::SendTo => match app.config.dialog_keys.get(key) {
+                    Some(Action::Select) => app.send_to_destination(),
+                    Some(Action::Back) => app.mode = app.normal_mode(),
+                    _ => match app.config.keys.get(key) {
+                        Some(Action::Up) => app.next_destination(false),
+                        Some(Action::Down) => app.next_destination(true),
+                        _ => {}
+                    },
+                },
+                EditorMode::Template => match app.config.dialog_keys.get(key) {
+                    Some(Action::Select) => app.apply_template(),
+                    Some(Action::Back) => app.close_templates(),
+                    _ => match app.config.keys.get(key) {
+                        Some(Action::Up) => app.next_template(false),
+                        Some(Action::Down) => app.next_template(true),
+                        _ => {}
+                    },
+                },
+                EditorMode::Conflict => match app.config.dialog_keys.get(key) {
+                    Some(Action::KeepMine) => app.resolve_conflict(Resolution::KeepMine),
+                    Some(Action::TakeTheirs) => app.resolve_conflict(Resolution::TakeTheirs),
+                    Some(Action::Merge)
+                        if app.conflicts.first().is_some_and(|c| c.notes.is_empty()) =>
+                    {
+                        app.resolve_conflict(Resolution::Merge)
                     }
-                    KeyCode::Char('-') => {
-                        if app.cursor_vertical == 2 {
-                            app.set_note_state(NoteEnum::REJECTED)
+                    _ => {}
+                },
+                EditorMode::Calendar => match app.config.calendar_keys.get(key) {
+                    Some(Action::PreviousMonth) => app.move_calendar_months(false),
+                    Some(Action::NextMonth) => app.move_calendar_months(true),
+                    Some(Action::Today) => app.calendar = Local::now().date_naive(),
+                    _ => match app.config.dialog_keys.get(key) {
+                        Some(Action::Select) => app.open_journal(app.calendar),
+                        Some(Action::Back) => app.mode = EditorMode::Nothing,
+                        _ => match app.config.keys.get(key) {
+                            Some(Action::Up) => app.move_calendar(-7),
+                            Some(Action::Down) => app.move_calendar(7),
+                            Some(Action::Left) => app.move_calendar(-1),
+                            Some(Action::Right) => app.move_calendar(1),
+                            _ => {}
+                        },
+                    },
+                },
+                EditorMode::Confirm => match (app.confirm, app.config.dialog_keys.get(key)) {
+                    (Some(Confirm::Quit(_)), Some(Action::SaveAndQuit)) => match app.save() {
+                        Ok(_) => return Ok(()),
+                        Err(err) => {
+                            app.cancel_confirm();
+                            app.message = Some(err);
                         }
-                    }
+                    },
+                    (Some(Confirm::Quit(_)), Some(Action::Discard)) => return Ok(()),
+                    (Some(Confirm::Remove), Some(Action::Confirm)) => app.confirm_remove(),
+                    (_, Some(Action::Back | Action::Cancel)) => app.cancel_confirm(),
                     _ => {}
                 },
                 _ => match key.code {
-                    KeyCode::Char('q') => {
-                        app.save();
-                        return Ok(());
+                    KeyCode::Enter
+                        if key.modifiers == KeyModifiers::ALT && app.mode.is_multiline() =>
+                    {
+                        app.input.insert('\n')
                     }
-                    KeyCode::Char('c') => {
-                        if key.modifiers == KeyModifiers::CONTROL {
-                            // app.save();
-                            return Ok(());
-                        } else {
-                            app.input.push('c');
-                        }
+                    KeyCode::Char('j')
+                        if key.modifiers == KeyModifiers::CONTROL && app.mode.is_multiline() =>
+                    {
+                        app.input.insert('\n')
                     }
-                    KeyCode::Char(c) => {
-                        app.input.push(c);
-                    }
-                    KeyCode::Backspace => {
-                        app.input.pop();
+                    KeyCode::Enter => {
+                        if !app.open_templates() {
+                            app.handle_enter()
+                        }
                     }
-                    KeyCode::Enter => app.handle_enter(),
-                    KeyCode::Esc => {
-                        app.mode = EditorMode::Nothing;
-                        app.input = String::new();
+                    KeyCode::Esc => app.cancel_input(),
+                    _ => {
+                        app.input.handle_key(key);
                     }
-                    _ => {}
                 },
             }
+            if app.config.options.autosave
+                && app.mode == EditorMode::Nothing
+                && app.has_unsaved_changes()
+            {
+                if let Err(err) = app.save() {
+                    app.message = Some(err);
+                }
+            }
         }
     }
 }
 
+/// Ctrl + c always quits, asking first if there are unsaved changes, whatever the key bindings
+/// are
+fn is_interrupt(key: KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL
+}
+
+fn handle_normal_action(app: &mut App, action: Action) {
+    let on_note = app.cursor_vertical == 2;
+    match action {
+        Action::CreateNote => app.create_note(),
+        Action::CreateFile => app.create_file(),
+        Action::CreateFolder => app.create_folder(),
+        Action::CreateList => app.create_list(),
+        Action::Undo => app.undo(),
+        Action::Redo => app.redo(),
+        Action::Remove => app.request_remove(),
+        Action::Edit => app.change(),
+        Action::EditDescription => app.change_description(),
+        Action::MoveUp => app.move_selected(true),
+        Action::MoveDown => app.move_selected(false),
+        Action::Up => app.navigate_up(),
+        Action::Down => app.navigate_down(),
+        Action::Left => app.previous(),
+        Action::Right => app.next(),
+        Action::Back => {
+            if app.search.is_some() {
+                app.clear_search();
+            } else if on_note {
+                app.note_index = 0;
+                app.navigate_up();
+            }
+        }
+        Action::Visual if on_note => app.start_visual(),
+        Action::Yank => app.yank(),
+        Action::Cut => app.cut(),
+        Action::Paste => app.paste(),
+        Action::MoveTo => app.open_send_to(false),
+        Action::CopyTo => app.open_send_to(true),
+        Action::Search => app.start_search(),
+        Action::NextMatch => app.next_match(true),
+        Action::PreviousMatch => app.next_match(false),
+        Action::SearchResults => app.open_search_results(),
+        Action::CycleState if on_note => app.cycle_note_state(),
+        Action::Open if on_note => app.set_note_state(NoteEnum::OPEN),
+        Action::InProgress if on_note => app.set_note_state(NoteEnum::PROGRESS),
+        Action::Defer if on_note => app.set_note_state(NoteEnum::DEFERRED),
+        Action::Block if on_note => app.set_note_state(NoteEnum::BLOCKED),
+        Action::Done if on_note => app.set_note_state(NoteEnum::DONE),
+        Action::Reject if on_note => app.set_note_state(NoteEnum::REJECTED),
+        Action::Indent if on_note => app.indent_note(),
+        Action::Outdent if on_note => app.outdent_note(),
+        Action::Collapse if on_note => app.toggle_collapse(),
+        Action::SortByPriority => app.sort_notes(SortKey::Priority),
+        Action::SortByDue => app.sort_notes(SortKey::Due),
+        Action::NextTheme => app.next_theme(),
+        Action::Journal => app.toggle_journal(),
+        Action::Calendar => app.open_calendar(),
+        Action::Save => app.save_with_message(),
+        Action::Help => app.help = Some(0),
+        _ => {}
+    }
+}
+
+fn handle_visual_action(app: &mut App, action: Action) {
+    match action {
+        Action::Up => app.previous_note(),
+        Action::Down => app.next_note(),
+        Action::Mark => app.toggle_mark(),
+        Action::MarkRange => app.toggle_range(),
+        Action::MarkAll => app.mark_all(None),
+        Action::MarkOpen => app.mark_all(Some(NoteEnum::OPEN)),
+        Action::MarkDone => app.mark_all(Some(NoteEnum::DONE)),
+        Action::MarkRejected => app.mark_all(Some(NoteEnum::REJECTED)),
+        Action::ClearMarks => app.clear_marks(),
+        Action::Open => app.set_note_state(NoteEnum::OPEN),
+        Action::InProgress => app.set_note_state(NoteEnum::PROGRESS),
+        Action::Defer => app.set_note_state(NoteEnum::DEFERRED),
+        Action::Block => app.set_note_state(NoteEnum::BLOCKED),
+        Action::Done => app.set_note_state(NoteEnum::DONE),
+        Action::Reject => app.set_note_state(NoteEnum::REJECTED),
+        Action::PriorityHigh => app.set_priority(Some(Priority::High)),
+        Action::PriorityMedium => app.set_priority(Some(Priority::Medium)),
+        Action::PriorityLow => app.set_priority(Some(Priority::Low)),
+        Action::PriorityNone => app.set_priority(None),
+        Action::Tag => app.mode = EditorMode::BulkTag,
+        Action::Remove => app.remove(),
+        Action::Yank => app.yank(),
+        Action::Cut => app.cut(),
+        Action::MoveTo => app.open_send_to(false),
+        Action::CopyTo => app.open_send_to(true),
+        Action::Back => app.stop_visual(),
+        Action::Help => app.help = Some(0),
+        _ => {}
+    }
+}
+
+/// Hand the terminal over to another program
+fn suspend<B: Backend>(terminal: &mut Terminal<B>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()
+}
+
+fn resume<B: Backend>(terminal: &mut Terminal<B>) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()
+}
+
 pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let size = f.size();
-    let chunks = if app.mode != EditorMode::Nothing {
+    let theme = app.config.theme.clone();
+    let input_height = if app.mode.is_multiline() {
+        app.input.line_count().clamp(1, MAX_INPUT_LINES) as u16 + 2
+    } else {
+        3
+    };
+    let chunks = if app.mode.has_input() {
         Layout::default()
             .direction(Direction::Vertical)
             .margin(2)
@@ -132,7 +348,7 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                     Constraint::Length(3),
                     Constraint::Length(3),
                     Constraint::Min(0),
-                    Constraint::Length(3),
+                    Constraint::Length(input_height),
                 ]
                 .as_ref(),
             )
@@ -152,8 +368,7 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
             .split(size)
     };
 
-    let block =
-        Block::default().style(Style::default().bg(Color::Rgb(31, 41, 55)).fg(Color::White));
+    let block = Block::default().style(Style::default().bg(theme.background).fg(theme.foreground));
     f.render_widget(block, size);
     let mut list_strings: Vec<String> = Vec::new();
     let ls = if !app.lists.is_empty() {
@@ -165,116 +380,711 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         list_strings.push(file.parse().unwrap());
     }
 
-    let list_tab_items = make_tab_items(&list_strings);
-    let file_tab_items = make_tab_items(app.files);
+    let list_tab_items = make_tab_items(&list_strings, &theme);
+    // Regular files or journal days, of which only those around the selected day fit
+    let mut tabs = app.tab_files();
+    let mut selected_tab = tabs.iter().position(|i| *i == app.file_index).unwrap_or(0);
+    if app.in_journal() {
+        let fitting = (chunks[0].width.saturating_sub(2) / JOURNAL_TAB_WIDTH).max(1) as usize;
+        let start = selected_tab
+            .saturating_sub(fitting / 2)
+            .min(tabs.len().saturating_sub(fitting));
+        tabs = tabs.into_iter().skip(start).take(fitting).collect();
+        selected_tab -= start;
+    }
+    let file_names: Vec<String> = tabs.iter().map(|i| app.files[*i].clone()).collect();
+    let file_tab_items = make_file_tab_items(&file_names, &theme);
 
     let mut list_tabs = Tabs::new(list_tab_items)
         .block(Block::default().borders(Borders::ALL).title("Lists"))
         .select(app.list_index)
-        .style(Style::default().fg(Color::Cyan));
+        .style(Style::default().fg(theme.accent));
 
     if app.cursor_vertical == 1 {
         list_tabs = list_tabs.highlight_style(
             Style::default()
                 .add_modifier(Modifier::BOLD)
-                .bg(Color::DarkGray),
+                .bg(theme.highlight),
         );
     }
 
     f.render_widget(list_tabs, chunks[1]);
 
+    let files_title = match app.config.keys.names(&[Action::Calendar]) {
+        keys if app.in_journal() && !keys.is_empty() => {
+            format!("Journal ({} for the calendar)", keys)
+        }
+        _ if app.in_journal() => "Journal".to_string(),
+        _ => "Files".to_string(),
+    };
     let mut file_tabs = Tabs::new(file_tab_items)
-        .block(Block::default().borders(Borders::ALL).title("Files"))
-        .select(app.file_index)
-        .style(Style::default().fg(Color::Cyan));
+        .block(Block::default().borders(Borders::ALL).title(files_title))
+        .select(selected_tab)
+        .style(Style::default().fg(theme.accent));
 
     if app.cursor_vertical == 0 {
         file_tabs = file_tabs.highlight_style(
             Style::default()
                 .add_modifier(Modifier::BOLD)
-                .bg(Color::DarkGray),
+                .bg(theme.highlight),
         );
     }
 
     f.render_widget(file_tabs, chunks[0]);
 
-    // NOTES
-    let items = app
+    // DESCRIPTION
+    let description = app
         .lists
         .get(app.file_index)
-        .unwrap_or(EMPTY_LIST)
-        .notes
-        .get(app.list_index)
-        .unwrap_or(EMPTY_NOTE_VEC);
-    let items: Vec<ListItem> = items
+        .and_then(|list| list.descriptions.get(app.list_index))
+        .map_or("", String::as_str);
+    let notes_area = if description.trim().is_empty() {
+        chunks[2]
+    } else {
+        let lines = wrapped_height(description, chunks[2].width.saturating_sub(2));
+        let height = (lines + 2).min(chunks[2].height / 3).max(3);
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(height), Constraint::Min(0)].as_ref())
+            .split(chunks[2]);
+        let paragraph = Paragraph::new(markdown_lines(description, &theme))
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("Description"));
+        f.render_widget(paragraph, areas[0]);
+        areas[1]
+    };
+
+    // NOTES
+    let today = Local::now().date_naive();
+    let notes = app.current_notes();
+    let marked = match app.selection {
+        Some(_) => app.selected_paths(),
+        None => vec![],
+    };
+    let items: Vec<ListItem> = app
+        .visible_paths()
         .iter()
         .enumerate()
-        .map(|(i, note)| {
-            let lines = if i == app.note_index && app.cursor_vertical == 2 {
-                vec![Spans::from(note.to_string_custom(">"))]
+        .map(|(i, path)| {
+            let note = note_at(notes, path).unwrap();
+            let is_marked = marked.contains(path);
+            let start = if i == app.note_index && app.cursor_vertical == 2 {
+                ">"
+            } else if is_marked {
+                "*"
+            } else {
+                "-"
+            };
+            let fold = match (note.children.is_empty(), note.collapsed) {
+                (true, _) => " ",
+                (false, true) => "▸",
+                (false, false) => "▾",
+            };
+            let mut spans = vec![
+                Span::raw(format!("{}{} {} ", "  ".repeat(note.depth), fold, start)),
+                Span::styled(note.checkbox(), state_style(&note.state, &theme)),
+                Span::raw(" "),
+            ];
+            spans.extend(content_spans(&note.content, today, &theme));
+            if !note.children.is_empty() {
+                let (done, total) = note.progress();
+                spans.push(Span::raw(format!(" ({}/{})", done, total)));
+            }
+            if let (NoteEnum::DONE, Some(date)) = (&note.state, note.meta.completed) {
+                spans.push(Span::styled(
+                    format!(" completed {}", relative_date(date, today)),
+                    Style::default().fg(theme.folder),
+                ));
+            }
+            let color = if is_marked {
+                theme.marked
             } else {
-                vec![Spans::from(note.to_string())]
+                theme.foreground
             };
-            ListItem::new(lines).style(Style::default().fg(Color::White))
+            ListItem::new(Spans::from(spans)).style(Style::default().fg(color))
         })
         .collect();
 
     let items = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title(
-            if !app.lists.is_empty() || !app.files.is_empty() {
-                &*app
-                    .lists
-                    .get(app.file_index)
-                    .unwrap()
-                    .descriptions
-                    .get(app.list_index)
-                    .unwrap_or(EMPTY_STRING)
-            } else {
-                ""
-            },
-        ))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(match (&app.search, app.mode) {
+                    _ if app.selection.is_some() => format!("Visual: {} selected", marked.len()),
+                    (Some(search), mode) if mode != EditorMode::Search => {
+                        format!("Search: {}", search.query)
+                    }
+                    _ => "Notes".to_string(),
+                }),
+        )
         .highlight_style(
             Style::default()
-                .bg(Color::DarkGray)
+                .bg(theme.highlight)
                 .add_modifier(Modifier::BOLD),
         );
 
-    f.render_stateful_widget(items, chunks[2], &mut app.notes_state);
+    f.render_stateful_widget(items, notes_area, &mut app.notes_state);
 
     // Input
-    if app.mode != EditorMode::Nothing {
-        let input = Paragraph::new(app.input.as_ref())
+    if app.mode == EditorMode::SearchResults {
+        render_search_results(f, app);
+    } else if app.mode == EditorMode::SendTo {
+        render_send_to(f, app);
+    } else if app.mode == EditorMode::Template {
+        render_templates(f, app);
+    } else if app.mode == EditorMode::Conflict {
+        render_conflict(f, app);
+    } else if app.mode == EditorMode::Confirm {
+        render_confirm(f, app);
+    } else if app.mode == EditorMode::Calendar {
+        render_calendar(f, app);
+    } else if app.mode.has_input() {
+        let area = chunks[3];
+        let (lines, column, row) = app.input.view_lines(
+            area.width.saturating_sub(2) as usize,
+            area.height.saturating_sub(2) as usize,
+        );
+        let text: Vec<Spans> = lines.into_iter().map(Spans::from).collect();
+        let input = Paragraph::new(text)
             .style(match app.mode {
                 EditorMode::Nothing => Style::default(),
-                _ => Style::default().fg(Color::White),
+                _ => Style::default().fg(theme.foreground),
             })
             .block(
                 Block::default()
                     .borders(Borders::ALL)
                     .title(match app.mode {
-                        EditorMode::CreateFile => "Create New File",
+                        EditorMode::CreateFile => "Create New File (folder/name for a folder)",
+                        EditorMode::CreateFolder => "Create New Folder",
                         EditorMode::CreateList => "Create New List",
                         EditorMode::CreateNote => "Create New Note",
                         EditorMode::ChangeFileName => "Change File Name",
                         EditorMode::ChangeListName => "Change List Name",
-                        EditorMode::ChangeListDescription => "Change List Description",
+                        EditorMode::ChangeListDescription => {
+                            "Change List Description (Alt + Enter / Ctrl + j for a new line)"
+                        }
                         EditorMode::ChangeNoteContent => "Change Note Content",
+                        EditorMode::BulkTag => "Add Tags (-tag removes)",
+                        EditorMode::Search => match &app.search {
+                            Some(search) if !search.is_valid() => "Search (invalid regex)",
+                            Some(search) if search.regex => "Search (regex, Ctrl + r for text)",
+                            _ => "Search (Ctrl + r for regex)",
+                        },
                         _ => "",
                     })
-                    .style(Style::default().fg(Color::LightCyan)),
+                    .style(Style::default().fg(theme.input)),
             );
-        f.render_widget(input, chunks[3]);
+        f.render_widget(input, area);
+        f.set_cursor(area.x + 1 + column as u16, area.y + 1 + row as u16);
+    }
+
+    // Status bar, shown in the bottom margin
+    if size.height > 2 && size.width > 4 {
+        let area = Rect::new(size.x + 2, size.bottom() - 2, size.width - 4, 1);
+        render_status(f, app, area);
+    }
+
+    if app.help.is_some() {
+        render_help(f, app);
     }
 }
 
-pub fn make_tab_items(v: &[String]) -> Vec<Spans> {
+/// Mode, level, unsaved changes and the states of the notes of the list, followed by the
+/// message of the last key press
+fn render_status<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let theme = &app.config.theme;
+    let notes = app.current_notes();
+    let level = match app.cursor_vertical {
+        0 => "files",
+        1 => "lists",
+        _ => "notes",
+    };
+    let mut spans = vec![
+        Span::styled(
+            format!(" {} ", app.mode.name()),
+            Style::default()
+                .fg(theme.background)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" {}", level)),
+    ];
+    if app.has_unsaved_changes() {
+        spans.push(Span::styled(
+            " ● unsaved",
+            Style::default().fg(theme.due).add_modifier(Modifier::BOLD),
+        ));
+    }
+    // Open notes are always counted, other states once there are any
+    let counts: Vec<String> = NoteEnum::ALL
+        .iter()
+        .map(|state| (state, count_notes(notes, state)))
+        .filter(|(state, count)| **state == NoteEnum::OPEN || *count > 0)
+        .map(|(state, count)| format!("{} {}", count, state.name()))
+        .collect();
+    spans.push(Span::styled(
+        format!("  {}", counts.join(", ")),
+        Style::default().fg(theme.folder),
+    ));
+    let status = Spans::from(spans);
+    let width = (status.width() as u16).min(area.width);
+    let areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(width), Constraint::Min(0)].as_ref())
+        .split(area);
+    f.render_widget(Paragraph::new(status), areas[0]);
+
+    if let Some(message) = &app.message {
+        let message = Paragraph::new(message.as_ref())
+            .alignment(Alignment::Right)
+            .style(
+                Style::default()
+                    .fg(theme.message)
+                    .add_modifier(Modifier::BOLD),
+            );
+        f.render_widget(message, areas[1]);
+    }
+}
+
+/// Keys of the current mode, scrolled by the up and down keys
+fn render_help<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let theme = app.config.theme.clone();
+    let entries = help_entries(app.mode, app.cursor_vertical, &app.config);
+    let width = entries
+        .iter()
+        .map(|(keys, _)| keys.width())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Spans> = entries
+        .into_iter()
+        .map(|(keys, description)| {
+            let padding = " ".repeat(width - keys.width() + 2);
+            Spans::from(vec![
+                Span::styled(keys, Style::default().fg(theme.accent)),
+                Span::raw(padding),
+                Span::styled(description, Style::default().fg(theme.foreground)),
+            ])
+        })
+        .collect();
+
+    let area = centered_rect(70, 70, f.size());
+    let visible = area.height.saturating_sub(2);
+    let scroll = app
+        .help
+        .unwrap_or(0)
+        .min((lines.len() as u16).saturating_sub(visible));
+    app.help = Some(scroll);
+    let help = Paragraph::new(lines)
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(
+                    "Help: {} (up and down scroll, any other key closes)",
+                    app.mode.name().to_lowercase()
+                ))
+                .style(Style::default().fg(theme.accent)),
+        )
+        .style(Style::default().bg(theme.background).fg(theme.foreground));
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+}
+
+fn render_search_results<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let theme = app.config.theme.clone();
+    let items: Vec<ListItem> = app
+        .search_results
+        .iter()
+        .map(|found| {
+            let note = note_at(
+                &app.lists[found.file_index].notes[found.list_index],
+                &found.path,
+            )
+            .unwrap();
+            ListItem::new(Spans::from(vec![
+                Span::styled(
+                    format!(
+                        "{} / {}: ",
+                        app.files[found.file_index],
+                        app.lists[found.file_index].titles[found.list_index]
+                    ),
+                    Style::default().fg(theme.accent),
+                ),
+                Span::raw(note.to_string()),
+            ]))
+        })
+        .collect();
+    let results = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} matches", app.search_results.len())),
+        )
+        .style(Style::default().bg(theme.background).fg(theme.foreground))
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        );
+    let area = centered_rect(80, 60, f.size());
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(results, area, &mut app.results_state);
+}
+
+fn render_send_to<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let theme = app.config.theme.clone();
+    let items: Vec<ListItem> = app
+        .destinations
+        .iter()
+        .map(|destination| {
+            let name = &app.files[destination.file_index];
+            ListItem::new(match destination.list_index {
+                Some(list_index) => Spans::from(vec![
+                    Span::styled(format!("{} / ", name), Style::default().fg(theme.accent)),
+                    Span::raw(app.lists[destination.file_index].titles[list_index].clone()),
+                ]),
+                None => Spans::from(name.clone()),
+            })
+        })
+        .collect();
+    let title = if app.send_copy { "Copy to" } else { "Move to" };
+    let destinations = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(Style::default().bg(theme.background).fg(theme.foreground))
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        );
+    let area = centered_rect(60, 60, f.size());
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(destinations, area, &mut app.destinations_state);
+}
+
+/// Templates for the new file or list, the first line of each shown after its name
+fn render_templates<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let theme = app.config.theme.clone();
+    let blank = if app.template_for == EditorMode::CreateList {
+        "Empty list"
+    } else {
+        "Default file"
+    };
+    let mut items = vec![ListItem::new(Spans::from(Span::styled(
+        blank,
+        Style::default().fg(theme.folder),
+    )))];
+    items.extend(app.templates.iter().map(|template| {
+        let first_line = template
+            .content
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("");
+        ListItem::new(Spans::from(vec![
+            Span::raw(template.name.clone()),
+            Span::styled(
+                format!("  {}", first_line),
+                Style::default().fg(theme.folder),
+            ),
+        ]))
+    }));
+    let title = format!("Template for {}", app.input.text());
+    let templates = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(Style::default().bg(theme.background).fg(theme.foreground))
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        );
+    let area = centered_rect(60, 60, f.size());
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(templates, area, &mut app.templates_state);
+}
+
+fn render_conflict<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let theme = app.config.theme.clone();
+    let conflict = match app.conflicts.first() {
+        Some(conflict) => conflict,
+        None => return,
+    };
+    let name = match app.paths.iter().position(|path| *path == conflict.path) {
+        Some(i) => app.files[i].as_str(),
+        None => "",
+    };
+    let mut text = vec![Spans::from(format!(
+        "{} was changed on disk while it has unsaved changes.",
+        name
+    ))];
+    let keys = &app.config.dialog_keys;
+    let answers = format!(
+        "{} = keep mine, {} = take theirs",
+        keys.names(&[Action::KeepMine]),
+        keys.names(&[Action::TakeTheirs])
+    );
+    if conflict.notes.is_empty() {
+        text.push(Spans::from(""));
+        text.push(Spans::from(format!(
+            "{}, {} = merge both",
+            answers,
+            keys.names(&[Action::Merge])
+        )));
+    } else {
+        text.push(Spans::from("Both sides changed these notes:"));
+        text.push(Spans::from(""));
+        for note in conflict.notes.iter() {
+            text.push(Spans::from(Span::styled(
+                note.clone(),
+                Style::default().fg(theme.marked),
+            )));
+        }
+        text.push(Spans::from(""));
+        text.push(Spans::from(answers));
+    }
+    let prompt = Paragraph::new(text)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Conflict")
+                .style(Style::default().fg(theme.message)),
+        )
+        .style(Style::default().bg(theme.background).fg(theme.foreground));
+    let area = centered_rect(60, 30, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_confirm<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let theme = app.config.theme.clone();
+    let keys = &app.config.dialog_keys;
+    let cancel = keys.names(&[Action::Back, Action::Cancel]);
+    let remove = format!(
+        "{} = remove, {} = keep",
+        keys.names(&[Action::Confirm]),
+        cancel
+    );
+    let (title, question, answers) = match app.confirm {
+        Some(Confirm::Quit(_)) => (
+            "Quit",
+            "There are unsaved changes.".to_string(),
+            format!(
+                "{} = save and quit, {} = discard and quit, {} = cancel",
+                keys.names(&[Action::SaveAndQuit]),
+                keys.names(&[Action::Discard]),
+                cancel
+            ),
+        ),
+        Some(Confirm::Remove) if app.cursor_vertical == 0 => (
+            "Remove",
+            format!(
+                "Remove the file {}?",
+                app.files.get(app.file_index).map_or("", String::as_str)
+            ),
+            remove,
+        ),
+        Some(Confirm::Remove) => (
+            "Remove",
+            format!(
+                "Remove the list {} with {} notes?",
+                app.lists[app.file_index].titles[app.list_index],
+                NoteEnum::ALL
+                    .iter()
+                    .map(|state| count_notes(app.current_notes(), state))
+                    .sum::<usize>()
+            ),
+            remove,
+        ),
+        None => return,
+    };
+    let text = vec![Spans::from(question), Spans::from(""), Spans::from(answers)];
+    let prompt = Paragraph::new(text)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .style(Style::default().fg(theme.message)),
+        )
+        .style(Style::default().bg(theme.background).fg(theme.foreground));
+    let area = centered_rect(60, 30, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+/// Month of the selected calendar day, days with a journal file stand out
+fn render_calendar<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let theme = app.config.theme.clone();
+    let today = Local::now().date_naive();
+    let selected = app.calendar;
+    let first = selected.with_day(1).unwrap();
+    let days: Vec<NaiveDate> = app
+        .files
+        .iter()
+        .filter_map(|name| journal_date(name))
+        .collect();
+
+    let mut lines = vec![Spans::from(Span::styled(
+        "Mo Tu We Th Fr Sa Su",
+        Style::default().fg(theme.folder),
+    ))];
+    let mut week: Vec<Span> = Vec::new();
+    for _ in 0..first.weekday().num_days_from_monday() {
+        week.push(Span::raw("   "));
+    }
+    let mut day = first;
+    while day.month() == first.month() {
+        let mut style = Style::default();
+        if days.contains(&day) {
+            style = style.fg(theme.accent).add_modifier(Modifier::BOLD);
+        }
+        if day == today {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+        if day == selected {
+            style = style.bg(theme.highlight);
+        }
+        week.push(Span::styled(format!("{:>2}", day.day()), style));
+        week.push(Span::raw(" "));
+        if day.weekday() == Weekday::Sun {
+            lines.push(Spans::from(std::mem::take(&mut week)));
+        }
+        day = day.succ_opt().unwrap();
+    }
+    if !week.is_empty() {
+        lines.push(Spans::from(week));
+    }
+
+    let calendar = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(selected.format("%B %Y").to_string())
+                .style(Style::default().fg(theme.accent)),
+        )
+        .style(Style::default().bg(theme.background).fg(theme.foreground));
+    let size = f.size();
+    let area = Rect::new(
+        size.x + size.width.saturating_sub(24) / 2,
+        size.y + size.height.saturating_sub(10) / 2,
+        24.min(size.width),
+        10.min(size.height),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(calendar, area);
+}
+
+/// Color of the checkbox of a note, open notes keep the color of the text
+fn state_style(state: &NoteEnum, theme: &Theme) -> Style {
+    let color = match state {
+        NoteEnum::OPEN => return Style::default(),
+        NoteEnum::PROGRESS => theme.in_progress,
+        NoteEnum::DEFERRED => theme.deferred,
+        NoteEnum::BLOCKED => theme.blocked,
+        NoteEnum::DONE => theme.done,
+        NoteEnum::REJECTED => theme.rejected,
+    };
+    Style::default().fg(color)
+}
+
+/// Number of rows the text takes up when wrapped to the given width
+fn wrapped_height(text: &str, width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    text.split('\n')
+        .map(|line| line.width().div_ceil(width).max(1) as u16)
+        .sum()
+}
+
+/// Rectangle of the given percentage of the available area, placed in its center
+pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
+
+/// Note content with due dates, priorities, tags and contexts highlighted
+pub fn content_spans(content: &str, today: NaiveDate, theme: &Theme) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    for (i, word) in content.split(' ').enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(" "));
+        }
+        let style = match parse_token(word, i == 0) {
+            Some(Token::Due(date)) if date < today => Style::default().fg(theme.overdue),
+            Some(Token::Due(_)) => Style::default().fg(theme.due),
+            Some(Token::Priority(Priority::High)) => Style::default()
+                .fg(theme.priority_high)
+                .add_modifier(Modifier::BOLD),
+            Some(Token::Priority(_)) => Style::default().fg(theme.priority),
+            Some(Token::Tag(_)) => Style::default().fg(theme.tag),
+            Some(Token::Context(_)) => Style::default().fg(theme.context),
+            Some(Token::Created(_) | Token::Completed(_)) => Style::default().fg(theme.folder),
+            Some(Token::Recurrence(_)) => Style::default().fg(theme.due),
+            None => Style::default(),
+        };
+        spans.push(Span::styled(word.to_string(), style));
+    }
+    spans
+}
+
+/// Tabs of files, the folder of a file is dimmed so that files of a folder form a group
+pub fn make_file_tab_items<'a>(v: &'a [String], theme: &Theme) -> Vec<Spans<'a>> {
+    v.iter()
+        .map(|t| {
+            let (folder, name) = t.split_at(t.rfind('/').map_or(0, |i| i + 1));
+            let (first, rest) = name.split_at(name.chars().next().map_or(0, char::len_utf8));
+            Spans::from(vec![
+                Span::styled(folder, Style::default().fg(theme.folder)),
+                Span::styled(first, Style::default().fg(theme.tab_first)),
+                Span::styled(rest, Style::default().fg(theme.tab_rest)),
+            ])
+        })
+        .collect()
+}
+
+pub fn make_tab_items<'a>(v: &'a [String], theme: &Theme) -> Vec<Spans<'a>> {
     v.iter()
         .map(|t| {
-            let (first, rest) = t.split_at(1);
+            let (first, rest) = t.split_at(t.chars().next().map_or(0, char::len_utf8));
             Spans::from(vec![
-                Span::styled(first, Style::default().fg(Color::Yellow)),
-                Span::styled(rest, Style::default().fg(Color::Green)),
+                Span::styled(first, Style::default().fg(theme.tab_first)),
+                Span::styled(rest, Style::default().fg(theme.tab_rest)),
             ])
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::theme::Theme;
+    use crate::ui::{make_file_tab_items, make_tab_items};
+
+    #[test]
+    fn tab_items_test() {
+        let theme = Theme::default();
+        let titles = vec![String::new(), "Ärger".to_string(), "日本".to_string()];
+        let items = make_tab_items(&titles, &theme);
+        assert_eq!(items[1].0[0].content, "Ä");
+        assert_eq!(items[2].0[1].content, "本");
+        let files = vec!["work/".to_string(), "work/ö".to_string()];
+        assert_eq!(make_file_tab_items(&files, &theme)[1].0[1].content, "ö");
+    }
+}
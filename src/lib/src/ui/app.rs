@@ -1,16 +1,33 @@
+use crate::config::Config;
+use crate::merge::merge_files;
+use crate::meta::{with_created, with_priority, with_tag, Priority, SortKey, DATE_FORMAT};
+use crate::template::{expand, load_templates, Template};
 use crate::todo::{
-    FileList, Note, NoteEnum, STARTER_FILE_DESCRIPTION, STARTER_FILE_NOTE, STARTER_FILE_TITLE,
+    carry_over, file_name, file_path, filter_notes, indent_note, journal_date, move_note, note_at,
+    note_at_mut, outdent_note, parse_lines, remove_note, repeat_completed, sort_notes,
+    stamp_completions, todo_files, trim_blank_lines, update_parents, validate_file_name,
+    visible_notes, write_order, FileList, Note, NoteEnum, DEFAULT_INDENT,
 };
+use crate::ui::editor::LineEditor;
+use crate::ui::external;
+use crate::ui::history::{Change, Cursor, Entry, History};
+use crate::ui::register::{Destination, Register};
+use crate::ui::search::{Search, SearchMatch};
+use crate::ui::selection::{top_level, Selection};
+use crate::ui::watch::{read_file, stamp, Conflict, DiskFile, Resolution, Stamp};
 use crate::util::calculate_hash;
+use chrono::{Duration, Local, Months, NaiveDate};
 use std::collections::HashMap;
-use std::fs::remove_file;
+use std::fs::{create_dir_all, remove_file};
 use std::path::{Path, PathBuf};
 use tui::widgets::ListState;
 
 pub const EMPTY_LIST: &FileList = &FileList {
+    preamble: vec![],
     titles: vec![],
     descriptions: vec![],
     notes: vec![],
+    layouts: vec![],
 };
 pub const EMPTY_NOTE_VEC: &Vec<Note> = &vec![];
 pub const EMPTY_STRING: &String = &String::new();
@@ -19,12 +36,77 @@ pub const EMPTY_STRING: &String = &String::new();
 pub enum EditorMode {
     Nothing,
     CreateFile,
+    CreateFolder,
     CreateList,
     CreateNote,
     ChangeFileName,
     ChangeListName,
     ChangeListDescription,
     ChangeNoteContent,
+    Search,
+    SearchResults,
+    Conflict,
+    SendTo,
+    Visual,
+    BulkTag,
+    Confirm,
+    Calendar,
+    Template,
+}
+
+/// Question answered in `EditorMode::Confirm`
+#[derive(Copy, Clone, PartialEq)]
+pub enum Confirm {
+    /// Quit with unsaved changes, asked in the given mode
+    Quit(EditorMode),
+    /// Remove the selected file or list
+    Remove,
+}
+
+impl EditorMode {
+    /// Name shown in the status bar
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditorMode::Nothing => "NORMAL",
+            EditorMode::Visual => "VISUAL",
+            EditorMode::Search => "SEARCH",
+            EditorMode::SearchResults => "MATCHES",
+            EditorMode::SendTo => "SEND TO",
+            EditorMode::Conflict => "CONFLICT",
+            EditorMode::BulkTag => "TAG",
+            EditorMode::Confirm => "CONFIRM",
+            EditorMode::Calendar => "CALENDAR",
+            EditorMode::Template => "TEMPLATE",
+            EditorMode::CreateFile
+            | EditorMode::CreateFolder
+            | EditorMode::CreateList
+            | EditorMode::CreateNote => "CREATE",
+            EditorMode::ChangeFileName
+            | EditorMode::ChangeListName
+            | EditorMode::ChangeListDescription
+            | EditorMode::ChangeNoteContent => "EDIT",
+        }
+    }
+
+    /// The input takes several lines of text
+    pub fn is_multiline(&self) -> bool {
+        *self == EditorMode::ChangeListDescription
+    }
+
+    /// Whether the mode reads text from the input box
+    pub fn has_input(&self) -> bool {
+        !matches!(
+            self,
+            EditorMode::Nothing
+                | EditorMode::SearchResults
+                | EditorMode::Conflict
+                | EditorMode::SendTo
+                | EditorMode::Visual
+                | EditorMode::Confirm
+                | EditorMode::Calendar
+                | EditorMode::Template
+        )
+    }
 }
 
 pub struct App<'a> {
@@ -32,7 +114,7 @@ pub struct App<'a> {
     pub files: &'a mut Vec<String>,
     pub paths: &'a mut Vec<PathBuf>,
     pub lists: &'a mut Vec<FileList>,
-    pub hashes: &'a mut HashMap<&'a PathBuf, u64>,
+    pub hashes: &'a mut HashMap<PathBuf, u64>,
     pub cursor_vertical: usize,
     pub list_index: usize,
     pub file_index: usize,
@@ -41,7 +123,38 @@ pub struct App<'a> {
     pub lists_state: ListState,
     pub notes_state: ListState,
     pub mode: EditorMode,
-    pub input: String,
+    pub input: LineEditor,
+    pub search: Option<Search>,
+    pub search_results: Vec<SearchMatch>,
+    pub results_state: ListState,
+    pub history: History,
+    /// Error or notice shown in the status bar until the next key press
+    pub message: Option<String>,
+    /// Scroll offset of the help while it is shown
+    pub help: Option<u16>,
+    /// Files as last read from or written to disk
+    pub disk: HashMap<PathBuf, DiskFile>,
+    pub conflicts: Vec<Conflict>,
+    pub register: Option<Register>,
+    pub destinations: Vec<Destination>,
+    pub destinations_state: ListState,
+    /// Whether the send to picker copies instead of moving
+    pub send_copy: bool,
+    /// Notes marked in visual mode
+    pub selection: Option<Selection>,
+    pub confirm: Option<Confirm>,
+    /// Day selected in the calendar
+    pub calendar: NaiveDate,
+    /// File to go back to when leaving the journal
+    pub previous_file: Option<String>,
+    /// Templates offered by the picker, preceded by a blank file or list
+    pub templates: Vec<Template>,
+    pub templates_state: ListState,
+    /// Template the file or list being created starts from
+    pub template: Option<Template>,
+    /// Mode the template picker was opened from
+    pub template_for: EditorMode,
+    pub config: Config,
     to_remove: &'a mut Vec<PathBuf>,
 }
 
@@ -50,10 +163,26 @@ impl<'a> App<'a> {
         files: &'a mut Vec<String>,
         paths: &'a mut Vec<PathBuf>,
         lists: &'a mut Vec<FileList>,
-        hashes: &'a mut HashMap<&'a PathBuf, u64>,
+        hashes: &'a mut HashMap<PathBuf, u64>,
         main_path: &'a Path,
         to_remove: &'a mut Vec<PathBuf>,
     ) -> App<'a> {
+        let disk = paths
+            .iter()
+            .zip(lists.iter())
+            .map(|(path, list)| {
+                let file = DiskFile {
+                    stamp: stamp(path),
+                    base: list.clone(),
+                };
+                (path.clone(), file)
+            })
+            .collect();
+        // Journal days sort first but are not where the app should start
+        let file_index = files
+            .iter()
+            .position(|name| journal_date(name).is_none())
+            .unwrap_or(0);
         App {
             main_path,
             hashes,
@@ -63,17 +192,269 @@ impl<'a> App<'a> {
             to_remove,
             cursor_vertical: 0,
             list_index: 0,
-            file_index: 0,
+            file_index,
             note_index: 0,
             files_state: Default::default(),
             lists_state: Default::default(),
             notes_state: Default::default(),
             mode: EditorMode::Nothing,
-            input: String::new(),
+            input: Default::default(),
+            search: None,
+            search_results: Vec::new(),
+            results_state: Default::default(),
+            history: Default::default(),
+            message: None,
+            help: None,
+            disk,
+            conflicts: Vec::new(),
+            register: None,
+            destinations: Vec::new(),
+            destinations_state: Default::default(),
+            send_copy: false,
+            selection: None,
+            confirm: None,
+            calendar: Local::now().date_naive(),
+            previous_file: None,
+            templates: Vec::new(),
+            templates_state: Default::default(),
+            template: None,
+            template_for: EditorMode::Nothing,
+            config: Config::default(),
+        }
+    }
+
+    /// Top level notes of the selected list
+    pub fn current_notes(&self) -> &Vec<Note> {
+        self.lists
+            .get(self.file_index)
+            .unwrap_or(EMPTY_LIST)
+            .notes
+            .get(self.list_index)
+            .unwrap_or(EMPTY_NOTE_VEC)
+    }
+
+    fn current_notes_mut(&mut self) -> Option<&mut Vec<Note>> {
+        self.lists
+            .get_mut(self.file_index)?
+            .notes
+            .get_mut(self.list_index)
+    }
+
+    /// Paths of the notes shown in the notes pane, limited to matches while searching
+    pub fn visible_paths(&self) -> Vec<Vec<usize>> {
+        match &self.search {
+            Some(search) if !search.query.is_empty() => {
+                filter_notes(self.current_notes(), &|note| search.matches(&note.content))
+            }
+            _ => visible_notes(self.current_notes()),
+        }
+    }
+
+    /// Path of the selected note within the tree of the selected list
+    pub fn current_note_path(&self) -> Option<Vec<usize>> {
+        self.visible_paths().get(self.note_index).cloned()
+    }
+
+    pub fn current_note(&self) -> Option<&Note> {
+        note_at(self.current_notes(), &self.current_note_path()?)
+    }
+
+    pub fn current_note_mut(&mut self) -> Option<&mut Note> {
+        let path = self.current_note_path()?;
+        note_at_mut(self.current_notes_mut()?, &path)
+    }
+
+    fn select_note_path(&mut self, path: &[usize]) {
+        if let Some(index) = self
+            .visible_paths()
+            .iter()
+            .position(|visible| visible == path)
+        {
+            self.note_index = index;
+            self.notes_state.select(Option::from(self.note_index));
+        }
+    }
+
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            vertical: self.cursor_vertical,
+            file_index: self.file_index,
+            list_index: self.list_index,
+            note_index: self.note_index,
+        }
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor_vertical = cursor.vertical;
+        self.file_index = cursor.file_index;
+        self.list_index = cursor.list_index;
+        self.note_index = cursor.note_index;
+        self.files_state.select(Option::from(self.file_index));
+        self.lists_state.select(Option::from(self.list_index));
+        if self.cursor_vertical == 2 {
+            self.notes_state.select(Option::from(self.note_index));
+        } else {
+            self.notes_state.select(None);
+        }
+        self.validate_and_update_indices();
+    }
+
+    fn record(&mut self, change: Change, before: Cursor) {
+        let after = self.cursor();
+        self.history.push(Entry {
+            change,
+            before,
+            after,
+        });
+    }
+
+    /// Run a modification of the selected file and record it for undo
+    fn edit<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        self.edit_files(&[self.file_index], f)
+    }
+
+    /// Run a modification of several files and record it for undo as a single change
+    fn edit_files<F: FnOnce(&mut Self)>(&mut self, indices: &[usize], f: F) {
+        let cursor = self.cursor();
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let before: Vec<(usize, FileList)> = indices
+            .iter()
+            .filter_map(|i| self.lists.get(*i).map(|list| (*i, list.clone())))
+            .collect();
+        if before.is_empty() {
+            return;
+        }
+        f(self);
+        let mut changes: Vec<Change> = before
+            .into_iter()
+            .filter_map(|(file_index, before)| {
+                let after = self.lists.get(file_index)?;
+                (after.to_string() != before.to_string()).then(|| Change::Edit {
+                    file_index,
+                    before,
+                    after: after.clone(),
+                })
+            })
+            .collect();
+        match changes.len() {
+            0 => {}
+            1 => self.record(changes.pop().unwrap(), cursor),
+            _ => self.record(Change::Group(changes), cursor),
+        }
+    }
+
+    fn insert_file(&mut self, index: usize, name: String, path: PathBuf, list: FileList) {
+        if let Some(position) = self.to_remove.iter().rposition(|file| *file == path) {
+            self.to_remove.remove(position);
+        }
+        self.files.insert(index, name);
+        self.paths.insert(index, path);
+        self.lists.insert(index, list);
+    }
+
+    fn take_file(&mut self, index: usize) -> (String, PathBuf, FileList) {
+        let path = self.paths.remove(index);
+        self.to_remove.push(path.clone());
+        (self.files.remove(index), path, self.lists.remove(index))
+    }
+
+    fn rename_file(&mut self, index: usize, name: String, path: PathBuf) {
+        if let Some(position) = self.to_remove.iter().rposition(|file| *file == path) {
+            self.to_remove.remove(position);
+        }
+        self.to_remove.push(self.paths[index].clone());
+        self.files[index] = name;
+        self.paths[index] = path;
+    }
+
+    fn swap_files(&mut self, index: usize, other: usize) {
+        self.files.swap(index, other);
+        self.paths.swap(index, other);
+        self.lists.swap(index, other);
+    }
+
+    fn apply(&mut self, change: &Change, forward: bool) {
+        match (change, forward) {
+            (Change::Group(changes), true) => {
+                for change in changes.iter() {
+                    self.apply(change, true);
+                }
+            }
+            (Change::Group(changes), false) => {
+                for change in changes.iter().rev() {
+                    self.apply(change, false);
+                }
+            }
+            (
+                Change::Edit {
+                    file_index, after, ..
+                },
+                true,
+            ) => self.lists[*file_index] = after.clone(),
+            (
+                Change::Edit {
+                    file_index, before, ..
+                },
+                false,
+            ) => self.lists[*file_index] = before.clone(),
+            (
+                Change::CreateFile {
+                    index,
+                    name,
+                    path,
+                    list,
+                },
+                true,
+            )
+            | (
+                Change::RemoveFile {
+                    index,
+                    name,
+                    path,
+                    list,
+                },
+                false,
+            ) => self.insert_file(*index, name.clone(), path.clone(), list.clone()),
+            (Change::CreateFile { index, .. }, false)
+            | (Change::RemoveFile { index, .. }, true) => {
+                self.take_file(*index);
+            }
+            (Change::SwapFiles { index, other }, _) => self.swap_files(*index, *other),
+            (Change::RenameFile { index, after, .. }, true) => {
+                self.rename_file(*index, after.0.clone(), after.1.clone())
+            }
+            (Change::RenameFile { index, before, .. }, false) => {
+                self.rename_file(*index, before.0.clone(), before.1.clone())
+            }
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(entry) = self.history.undo() {
+            self.apply(&entry.change, false);
+            self.set_cursor(entry.before);
+            self.history.undone(entry);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(entry) = self.history.redo() {
+            self.apply(&entry.change, true);
+            self.set_cursor(entry.after);
+            self.history.redone(entry);
         }
     }
 
     fn validate_and_update_indices(&mut self) {
+        if self.files.is_empty() {
+            self.file_index = 0;
+            self.list_index = 0;
+            self.note_index = 0;
+            self.cursor_vertical = 0;
+            return;
+        }
         let size = self.files.len();
         if self.file_index >= size - 1 {
             self.file_index = size - 1;
@@ -81,21 +462,14 @@ impl<'a> App<'a> {
         }
 
         let size = self.lists.get(self.file_index).unwrap().titles.len();
-        if self.list_index > 0 && self.list_index > size - 1 {
-            self.list_index = size - 1;
+        if self.list_index > 0 && self.list_index > size.saturating_sub(1) {
+            self.list_index = size.saturating_sub(1);
             self.lists_state.select(Option::from(self.list_index));
         }
 
-        let size = self
-            .lists
-            .get(self.file_index)
-            .unwrap()
-            .notes
-            .get(self.list_index)
-            .unwrap_or(EMPTY_NOTE_VEC)
-            .len();
-        if self.note_index > 0 && self.note_index > size - 1 {
-            self.note_index = size - 1;
+        let size = self.visible_paths().len();
+        if self.note_index > 0 && self.note_index > size.saturating_sub(1) {
+            self.note_index = size.saturating_sub(1);
             self.notes_state.select(Option::from(self.note_index));
         }
     }
@@ -152,15 +526,47 @@ impl<'a> App<'a> {
         self.validate_and_update_indices();
     }
 
+    /// Whether the selected file is a journal day
+    pub fn in_journal(&self) -> bool {
+        self.files
+            .get(self.file_index)
+            .is_some_and(|name| journal_date(name).is_some())
+    }
+
+    /// Indices of the files shown as tabs, the journal days in order while one of them is
+    /// selected and the other files otherwise
+    pub fn tab_files(&self) -> Vec<usize> {
+        let journal = self.in_journal();
+        let mut indices: Vec<usize> = (0..self.files.len())
+            .filter(|i| journal_date(&self.files[*i]).is_some() == journal)
+            .collect();
+        if journal {
+            indices.sort_by(|a, b| self.files[*a].cmp(&self.files[*b]));
+        }
+        indices
+    }
+
+    /// Select the neighbouring file tab
+    fn step_file(&mut self, forward: bool) {
+        let tabs = self.tab_files();
+        let position = tabs.iter().position(|i| *i == self.file_index).unwrap_or(0);
+        let next = if forward {
+            tabs.get(position + 1)
+        } else {
+            position
+                .checked_sub(1)
+                .and_then(|position| tabs.get(position))
+        };
+        if let Some(index) = next {
+            self.file_index = *index;
+            self.note_index = 0;
+            self.notes_state.select(None);
+        }
+    }
+
     pub fn next(&mut self) {
         match self.cursor_vertical {
-            0 => {
-                self.file_index += 1;
-                self.note_index = 0;
-                self.files_state.select(Option::from(self.list_index));
-                // self.notes_state.select(Option::from(self.note_index));
-                self.notes_state.select(None);
-            }
+            0 => self.step_file(true),
             1 => {
                 self.list_index += 1;
                 self.note_index = 0;
@@ -172,14 +578,7 @@ impl<'a> App<'a> {
 
     pub fn previous(&mut self) {
         match self.cursor_vertical {
-            0 => {
-                if self.file_index > 0 {
-                    self.file_index -= 1;
-                    self.note_index = 0;
-                    self.files_state.select(Option::from(self.list_index));
-                    self.notes_state.select(None);
-                }
-            }
+            0 => self.step_file(false),
             1 => {
                 if self.list_index > 0 {
                     self.list_index -= 1;
@@ -208,163 +607,1226 @@ impl<'a> App<'a> {
     }
 
     pub fn cycle_note_state(&mut self) {
-        let note = self
-            .lists
-            .get_mut(self.file_index)
-            .unwrap()
-            .notes
-            .get_mut(self.list_index)
-            .unwrap()
-            .get_mut(self.note_index)
-            .unwrap();
-        match note.state {
-            NoteEnum::OPEN => note.set_state(NoteEnum::DONE),
-            NoteEnum::DONE => note.set_state(NoteEnum::REJECTED),
-            NoteEnum::REJECTED => note.set_state(NoteEnum::OPEN),
+        let state = match self.current_note() {
+            Some(note) => {
+                let cycle = &self.config.options.cycle;
+                match cycle.iter().position(|state| *state == note.state) {
+                    Some(i) => cycle[(i + 1) % cycle.len()].clone(),
+                    None => cycle[0].clone(),
+                }
+            }
+            None => return,
         };
+        self.set_note_state(state);
     }
 
+    /// Set the state of the selected notes, closing their subtasks as well
     pub fn set_note_state(&mut self, state: NoteEnum) {
-        let note = self
-            .lists
-            .get_mut(self.file_index)
-            .unwrap()
-            .notes
-            .get_mut(self.list_index)
-            .unwrap()
-            .get_mut(self.note_index)
-            .unwrap();
-        note.set_state(state);
+        let paths = self.selected_paths();
+        let record_dates = self.config.options.record_dates;
+        // Next occurrences are inserted below, a range is turned into marks which move along
+        let visible = self.visible_paths();
+        if let Some(selection) = &mut self.selection {
+            if selection.anchor.is_some() {
+                selection.close_range(&visible, self.note_index);
+            }
+        }
+        self.edit(|app| {
+            let notes = app.current_notes_mut().unwrap();
+            let before = notes.clone();
+            for path in paths.iter() {
+                let note = note_at_mut(notes, path).unwrap();
+                if state.is_closed() {
+                    note.close_children(&state);
+                }
+                note.set_state(state.clone());
+                update_parents(notes, path);
+            }
+            let today = Local::now().date_naive();
+            if record_dates {
+                stamp_completions(&before, notes, today);
+            }
+            // Recurring notes which were completed now continue with their next occurrence
+            let inserted = repeat_completed(&before, notes, today);
+            if let Some(selection) = &mut app.selection {
+                inserted.iter().for_each(|path| selection.insert(path));
+            }
+        });
+    }
+
+    /// Change the content of every selected note
+    fn map_contents<F: Fn(&str) -> String>(&mut self, f: F) {
+        let paths = self.selected_paths();
+        self.edit(|app| {
+            let notes = app.current_notes_mut().unwrap();
+            for path in paths.iter() {
+                let note = note_at_mut(notes, path).unwrap();
+                let content = f(&note.content);
+                if content != note.content {
+                    note.set_content(content);
+                }
+            }
+        });
+    }
+
+    pub fn set_priority(&mut self, priority: Option<Priority>) {
+        self.map_contents(|content| with_priority(content, priority));
+    }
+
+    /// Add `#tags` and `@contexts` separated by whitespace, `-tag` removes a tag. All of them
+    /// are undone together.
+    pub fn apply_tags(&mut self, input: &str) {
+        let tags: Vec<(String, bool)> = input
+            .split_whitespace()
+            .map(|word| match word.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (word, true),
+            })
+            .map(|(name, add)| {
+                if name.starts_with('#') || name.starts_with('@') {
+                    (name.to_string(), add)
+                } else {
+                    (format!("#{}", name), add)
+                }
+            })
+            .filter(|(tag, _)| tag.len() > 1)
+            .collect();
+        if tags.is_empty() {
+            return;
+        }
+        self.map_contents(|content| {
+            tags.iter()
+                .fold(content.to_string(), |content, (tag, add)| {
+                    with_tag(&content, tag, *add)
+                })
+        });
+    }
+
+    /// Notes the next action applies to, the marked notes in visual mode and the selected
+    /// note otherwise
+    pub fn selected_paths(&self) -> Vec<Vec<usize>> {
+        match &self.selection {
+            Some(selection)
+                if selection.file_index == self.file_index
+                    && selection.list_index == self.list_index =>
+            {
+                selection.paths(&self.visible_paths(), self.note_index)
+            }
+            Some(_) => vec![],
+            None => self.current_note_path().into_iter().collect(),
+        }
+    }
+
+    /// Mode to return to once a prompt or picker closes
+    pub fn normal_mode(&self) -> EditorMode {
+        if self.selection.is_some() {
+            EditorMode::Visual
+        } else {
+            EditorMode::Nothing
+        }
+    }
+
+    /// Enter visual mode with the selected note marked
+    pub fn start_visual(&mut self) {
+        if let Some(path) = self.current_note_path() {
+            let mut selection = Selection::new(self.file_index, self.list_index);
+            selection.toggle(&path);
+            self.selection = Some(selection);
+            self.mode = EditorMode::Visual;
+        }
+    }
+
+    pub fn stop_visual(&mut self) {
+        self.selection = None;
+        self.mode = EditorMode::Nothing;
+    }
+
+    pub fn toggle_mark(&mut self) {
+        if let (Some(path), Some(selection)) = (self.current_note_path(), &mut self.selection) {
+            selection.toggle(&path);
+        }
+    }
+
+    /// Start a range at the selected note, or mark the notes of the current range
+    pub fn toggle_range(&mut self) {
+        let visible = self.visible_paths();
+        if let Some(selection) = &mut self.selection {
+            match selection.anchor {
+                Some(_) => selection.close_range(&visible, self.note_index),
+                None => selection.anchor = Some(self.note_index),
+            }
+        }
+    }
+
+    /// Mark every visible note matching the filter, all of them without one
+    pub fn mark_all(&mut self, state: Option<NoteEnum>) {
+        let notes = self.current_notes();
+        let marked: Vec<Vec<usize>> = self
+            .visible_paths()
+            .into_iter()
+            .filter(|path| {
+                state
+                    .as_ref()
+                    .is_none_or(|state| note_at(notes, path).unwrap().state == *state)
+            })
+            .collect();
+        if let Some(selection) = &mut self.selection {
+            selection.marked = marked;
+            selection.anchor = None;
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        if let Some(selection) = &mut self.selection {
+            selection.marked.clear();
+            selection.anchor = None;
+        }
+    }
+
+    pub fn indent_note(&mut self) {
+        if let Some(path) = self.current_note_path() {
+            self.edit(|app| {
+                if let Some(path) = indent_note(app.current_notes_mut().unwrap(), &path) {
+                    app.select_note_path(&path);
+                }
+            });
+        }
+    }
+
+    pub fn outdent_note(&mut self) {
+        if let Some(path) = self.current_note_path() {
+            self.edit(|app| {
+                if let Some(path) = outdent_note(app.current_notes_mut().unwrap(), &path) {
+                    app.select_note_path(&path);
+                }
+            });
+        }
+    }
+
+    pub fn sort_notes(&mut self, key: SortKey) {
+        self.edit(|app| {
+            if let Some(notes) = app.current_notes_mut() {
+                sort_notes(notes, key);
+            }
+        });
+    }
+
+    /// Move the selected file, list or note one place up or down
+    pub fn move_selected(&mut self, up: bool) {
+        match self.cursor_vertical {
+            0 => {
+                let index = self.file_index;
+                let other = if up { index.wrapping_sub(1) } else { index + 1 };
+                if other >= self.files.len() {
+                    return;
+                }
+                let cursor = self.cursor();
+                self.swap_files(index, other);
+                self.file_index = other;
+                self.files_state.select(Option::from(self.file_index));
+                self.record(Change::SwapFiles { index, other }, cursor);
+            }
+            1 => {
+                let index = self.list_index;
+                let other = if up { index.wrapping_sub(1) } else { index + 1 };
+                let size = match self.lists.get(self.file_index) {
+                    Some(list) => list.titles.len(),
+                    None => return,
+                };
+                if other >= size {
+                    return;
+                }
+                self.edit(|app| {
+                    app.lists[app.file_index].swap(index, other);
+                    app.list_index = other;
+                    app.lists_state.select(Option::from(app.list_index));
+                });
+            }
+            2 => {
+                if let Some(path) = self.current_note_path() {
+                    self.edit(|app| {
+                        if let Some(new_path) =
+                            move_note(app.current_notes_mut().unwrap(), &path, up)
+                        {
+                            app.select_note_path(&new_path);
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    pub fn toggle_collapse(&mut self) {
+        if let Some(note) = self.current_note_mut() {
+            if !note.children.is_empty() {
+                note.collapsed = !note.collapsed;
+            }
+        }
     }
-    pub fn save(&self) {
+
+    pub fn start_search(&mut self) {
+        let query = self
+            .search
+            .as_ref()
+            .map(|search| search.query.clone())
+            .unwrap_or_default();
+        self.input.set(&query);
+        self.mode = EditorMode::Search;
+        self.update_search();
+    }
+
+    /// Filter the notes with the current input as it is typed
+    pub fn update_search(&mut self) {
+        let regex = self.search.as_ref().is_some_and(|search| search.regex);
+        self.search = Some(Search::new(self.input.text(), regex));
+        self.note_index = 0;
+        if self.cursor_vertical == 2 {
+            if self.visible_paths().is_empty() {
+                self.cursor_vertical = 1;
+                self.notes_state.select(None);
+            } else {
+                self.notes_state.select(Option::from(self.note_index));
+            }
+        }
+    }
+
+    pub fn toggle_search_regex(&mut self) {
+        if let Some(search) = &self.search {
+            self.search = Some(Search::new(&search.query, !search.regex));
+        }
+    }
+
+    pub fn confirm_search(&mut self) {
+        self.mode = EditorMode::Nothing;
+        self.input.clear();
+        if self
+            .search
+            .as_ref()
+            .is_none_or(|search| search.query.is_empty())
+        {
+            self.search = None;
+        } else if self.cursor_vertical != 2 {
+            self.next_match(true);
+        }
+    }
+
+    pub fn clear_search(&mut self) {
+        let path = self.current_note_path();
+        self.search = None;
+        self.mode = EditorMode::Nothing;
+        self.input.clear();
+        match path {
+            Some(path) if self.cursor_vertical == 2 => self.select_note_path(&path),
+            _ => self.note_index = 0,
+        }
+    }
+
+    fn find_matches(&self) -> Vec<SearchMatch> {
+        match &self.search {
+            Some(search) if !search.query.is_empty() => search.find(self.lists),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Jump to the next or previous match across all files
+    pub fn next_match(&mut self, forward: bool) {
+        let matches = self.find_matches();
+        if matches.is_empty() {
+            return;
+        }
+        let position = |m: &SearchMatch| (m.file_index, m.list_index, m.path.clone());
+        let current = (
+            self.file_index,
+            self.list_index,
+            match self.cursor_vertical {
+                2 => self.current_note_path().unwrap_or_default(),
+                _ => vec![],
+            },
+        );
+        let found = if forward {
+            matches
+                .iter()
+                .find(|m| position(m) > current)
+                .unwrap_or(matches.first().unwrap())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|m| position(m) < current)
+                .unwrap_or(matches.last().unwrap())
+        };
+        self.jump_to(&found.clone());
+    }
+
+    pub fn open_search_results(&mut self) {
+        self.search_results = self.find_matches();
+        if !self.search_results.is_empty() {
+            self.results_state.select(Some(0));
+            self.mode = EditorMode::SearchResults;
+        }
+    }
+
+    pub fn next_search_result(&mut self, forward: bool) {
+        let size = self.search_results.len();
+        let index = self.results_state.selected().unwrap_or(0);
+        let index = if forward {
+            (index + 1).min(size - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+        self.results_state.select(Some(index));
+    }
+
+    pub fn jump_to_search_result(&mut self) {
+        let index = self.results_state.selected().unwrap_or(0);
+        if let Some(found) = self.search_results.get(index).cloned() {
+            self.jump_to(&found);
+        }
+        self.mode = EditorMode::Nothing;
+    }
+
+    /// Move all cursors to a note, expanding its parents
+    pub fn jump_to(&mut self, found: &SearchMatch) {
+        self.file_index = found.file_index;
+        self.list_index = found.list_index;
+        let notes = self.current_notes_mut().unwrap();
+        for depth in 1..found.path.len() {
+            note_at_mut(notes, &found.path[..depth]).unwrap().collapsed = false;
+        }
+        self.cursor_vertical = 2;
+        self.select_note_path(&found.path);
+    }
+
+    /// Copy the selected list or note with its subtasks into the register
+    pub fn yank(&mut self) {
+        let register = match self.cursor_vertical {
+            1 => match self.lists.get(self.file_index) {
+                Some(list) if self.list_index < list.titles.len() => {
+                    Register::Lists(list.extract(self.list_index))
+                }
+                _ => return,
+            },
+            2 => {
+                let notes = self.current_notes();
+                let paths = top_level(&self.selected_paths());
+                if paths.is_empty() {
+                    return;
+                }
+                let notes = paths
+                    .iter()
+                    .map(|path| note_at(notes, path).unwrap().clone())
+                    .collect();
+                Register::Notes(notes)
+            }
+            _ => return,
+        };
+        self.message = Some(format!("Yanked {}", register.describe()));
+        self.register = Some(register);
+    }
+
+    /// Move the selected list or note into the register
+    pub fn cut(&mut self) {
+        if self.cursor_vertical == 1 || self.cursor_vertical == 2 {
+            self.yank();
+            self.remove();
+            if let Some(register) = &self.register {
+                self.message = Some(format!("Cut {}", register.describe()));
+            }
+        }
+    }
+
+    /// Insert the register below the selected note or list
+    pub fn paste(&mut self) {
+        let register = match self.register.clone() {
+            Some(register) => register,
+            None => return,
+        };
+        match register {
+            Register::Notes(notes) => {
+                if self
+                    .lists
+                    .get(self.file_index)
+                    .is_none_or(|list| list.titles.is_empty())
+                {
+                    return;
+                }
+                self.edit(|app| app.paste_notes(notes));
+            }
+            Register::Lists(lists) => {
+                let index = match self.lists.get(self.file_index) {
+                    Some(list) if self.cursor_vertical > 0 && !list.titles.is_empty() => {
+                        self.list_index + 1
+                    }
+                    Some(list) => list.titles.len(),
+                    None => return,
+                };
+                self.edit(|app| {
+                    app.lists[app.file_index].insert_lists(index, lists);
+                    app.list_index = index;
+                    app.cursor_vertical = 1;
+                    app.lists_state.select(Option::from(app.list_index));
+                });
+            }
+        }
+    }
+
+    /// Notes are placed below the selected note at its level, or at the end of the list
+    fn paste_notes(&mut self, notes: Vec<Note>) {
+        let (parent, index) = match self.current_note_path() {
+            Some(mut path) if self.cursor_vertical == 2 => {
+                let index = path.pop().unwrap() + 1;
+                (path, index)
+            }
+            _ => (vec![], self.current_notes().len()),
+        };
+        self.insert_notes(parent, index, notes);
+    }
+
+    /// Insert notes among the subtasks of a parent and select the first of them
+    fn insert_notes(&mut self, parent: Vec<usize>, index: usize, notes: Vec<Note>) {
+        if notes.is_empty() {
+            return;
+        }
+        let current_notes = self.current_notes_mut().unwrap();
+        let siblings = match note_at_mut(current_notes, &parent) {
+            Some(note) => &mut note.children,
+            None => &mut *current_notes,
+        };
+        for (offset, mut note) in notes.into_iter().enumerate() {
+            note.set_depth(parent.len());
+            siblings.insert(index + offset, note);
+        }
+        let mut first = parent;
+        first.push(index);
+        update_parents(current_notes, &first);
+        self.cursor_vertical = 2;
+        self.select_note_path(&first);
+    }
+
+    /// Edit the selected file, list or note with its subtasks as Markdown in the editor of
+    /// the user. The terminal has to be handed over to the editor beforehand.
+    pub fn edit_externally(&mut self) {
+        let list = match self.lists.get(self.file_index) {
+            Some(list) => list,
+            None => return,
+        };
+        let content = match self.cursor_vertical {
+            0 => list.to_string(),
+            1 if self.list_index < list.titles.len() => list.extract(self.list_index).to_string(),
+            2 => match self.current_note() {
+                Some(note) => {
+                    let mut note = note.clone();
+                    note.set_depth(0);
+                    let mut lines = Vec::new();
+                    let indent = list
+                        .layouts
+                        .get(self.list_index)
+                        .map_or(DEFAULT_INDENT, |layout| layout.indent.as_str());
+                    note.write_lines(indent, &mut lines);
+                    lines.push(String::new());
+                    lines.join("\n")
+                }
+                None => return,
+            },
+            _ => return,
+        };
+        let content = match external::edit(&content, &self.files[self.file_index]) {
+            Ok(content) => content,
+            Err(err) => {
+                self.message = Some(err);
+                return;
+            }
+        };
+        let lines: Vec<String> = content.split('\n').map(String::from).collect();
+
+        match self.cursor_vertical {
+            0 => self.edit(|app| {
+                app.lists[app.file_index] = parse_lines(lines);
+                app.validate_and_update_indices();
+            }),
+            1 => self.edit(|app| {
+                let current_list = &mut app.lists[app.file_index];
+                current_list.remove(app.list_index);
+                current_list.insert_lists(app.list_index, parse_lines(lines));
+                if current_list.titles.is_empty() {
+                    app.cursor_vertical = 0;
+                }
+                app.validate_and_update_indices();
+            }),
+            _ => self.edit(|app| {
+                // Notes need a list to be parsed into, text outside of notes has no place
+                let list = parse_lines([vec!["# Note".to_string()], lines].concat());
+                if list.titles.len() > 1 || list.descriptions.iter().any(|d| !d.trim().is_empty()) {
+                    app.message =
+                        Some("Headings and text before the note were left out".to_string());
+                }
+                let notes: Vec<Note> = list.notes.into_iter().flatten().collect();
+                let mut path = app.current_note_path().unwrap();
+                let index = path.pop().unwrap();
+                let mut note_path = path.clone();
+                note_path.push(index);
+                app.remove_notes(&[note_path]);
+                app.insert_notes(path, index, notes);
+            }),
+        }
+    }
+
+    /// Open the picker for sending the selected list to another file or the selected note to
+    /// another list
+    pub fn open_send_to(&mut self, copy: bool) {
+        self.destinations = match self.cursor_vertical {
+            1 if self
+                .lists
+                .get(self.file_index)
+                .is_some_and(|l| !l.titles.is_empty()) =>
+            {
+                (0..self.files.len())
+                    .filter(|i| *i != self.file_index)
+                    .map(|file_index| Destination {
+                        file_index,
+                        list_index: None,
+                    })
+                    .collect()
+            }
+            2 if !self.selected_paths().is_empty() => self
+                .lists
+                .iter()
+                .enumerate()
+                .flat_map(|(file_index, list)| {
+                    (0..list.titles.len()).map(move |list_index| Destination {
+                        file_index,
+                        list_index: Some(list_index),
+                    })
+                })
+                .filter(|destination| {
+                    *destination
+                        != Destination {
+                            file_index: self.file_index,
+                            list_index: Some(self.list_index),
+                        }
+                })
+                .collect(),
+            _ => return,
+        };
+        if self.destinations.is_empty() {
+            self.message = Some("There is nowhere to send this to".to_string());
+            return;
+        }
+        self.send_copy = copy;
+        self.destinations_state.select(Some(0));
+        self.mode = EditorMode::SendTo;
+    }
+
+    pub fn next_destination(&mut self, forward: bool) {
+        let size = self.destinations.len();
+        let index = self.destinations_state.selected().unwrap_or(0);
+        let index = if forward {
+            (index + 1).min(size - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+        self.destinations_state.select(Some(index));
+    }
+
+    /// Move or copy the selection to the destination chosen in the picker
+    pub fn send_to_destination(&mut self) {
+        self.mode = self.normal_mode();
+        let index = self.destinations_state.selected().unwrap_or(0);
+        if let Some(destination) = self.destinations.get(index) {
+            self.send_to(*destination, self.send_copy);
+        }
+    }
+
+    /// Move or copy the selected list to another file or the selected notes to another list
+    pub fn send_to(&mut self, destination: Destination, copy: bool) {
+        let source = self.file_index;
+        match (self.cursor_vertical, destination.list_index) {
+            (1, None) => self.edit_files(&[source, destination.file_index], |app| {
+                let lists = app.lists[source].extract(app.list_index);
+                if !copy {
+                    app.remove_list();
+                }
+                let target = &mut app.lists[destination.file_index];
+                target.insert_lists(target.titles.len(), lists);
+            }),
+            (2, Some(list_index)) => self.edit_files(&[source, destination.file_index], |app| {
+                let paths = top_level(&app.selected_paths());
+                let notes: Vec<Note> = paths
+                    .iter()
+                    .map(|path| note_at(app.current_notes(), path).unwrap().clone())
+                    .collect();
+                if !copy {
+                    app.remove_notes(&paths);
+                }
+                for mut note in notes {
+                    note.set_depth(0);
+                    app.lists[destination.file_index].notes[list_index].push(note);
+                }
+            }),
+            _ => return,
+        }
+        let name = match destination.list_index {
+            Some(list_index) => format!(
+                "{} / {}",
+                self.files[destination.file_index],
+                self.lists[destination.file_index].titles[list_index]
+            ),
+            None => self.files[destination.file_index].clone(),
+        };
+        let verb = if copy { "Copied" } else { "Moved" };
+        self.message = Some(format!("{} to {}", verb, name));
+    }
+
+    /// Whether a file has changes which are not saved yet
+    pub fn is_dirty(&self, index: usize) -> bool {
+        let stored_hash = self.hashes.get(&self.paths[index]).unwrap_or(&0);
+        *stored_hash != calculate_hash(&self.lists[index])
+    }
+
+    /// Switch to the next built-in theme
+    pub fn next_theme(&mut self) {
+        let name = self.config.theme.next_name();
+        match self.config.set_theme(name) {
+            Ok(()) => self.message = Some(format!("Theme: {}", name)),
+            Err(err) => self.message = Some(err),
+        }
+    }
+
+    /// Whether saving would write or remove any file
+    pub fn has_unsaved_changes(&self) -> bool {
+        (0..self.lists.len()).any(|i| self.is_dirty(i))
+            || self
+                .to_remove
+                .iter()
+                .any(|path| !self.paths.contains(path) && path.exists())
+    }
+
+    fn mark_on_disk(&mut self, path: PathBuf, list: FileList, stamp: Stamp) {
+        self.hashes.insert(path.clone(), calculate_hash(&list));
+        self.disk.insert(path, DiskFile { stamp, base: list });
+    }
+
+    /// Replace the content of a file with a version from disk, keeping the cursor in place.
+    /// Changes made to the file before can no longer be undone, the other files keep theirs.
+    fn replace_file(&mut self, index: usize, list: FileList) {
+        let cursor = self.cursor();
+        self.lists[index] = list;
+        self.set_cursor(cursor);
+        self.history.forget_file(index);
+    }
+
+    /// Pick up changes made to the data directory by other programs. Files without unsaved
+    /// changes are reloaded, the others are queued as conflicts.
+    pub fn check_external_changes(&mut self) {
+        let mut messages: Vec<String> = Vec::new();
+        let mut deleted: Vec<usize> = Vec::new();
+        for i in 0..self.paths.len() {
+            let path = self.paths[i].clone();
+            let known = match self.disk.get(&path) {
+                Some(file) => file.stamp,
+                None => continue,
+            };
+            let current = stamp(&path);
+            if current == known || self.conflicts.iter().any(|c| c.path == path) {
+                continue;
+            }
+            if current.is_none() {
+                if self.is_dirty(i) {
+                    messages.push(format!(
+                        "{} was deleted, saving recreates it",
+                        self.files[i]
+                    ));
+                    self.disk.remove(&path);
+                } else {
+                    deleted.push(i);
+                }
+                continue;
+            }
+            let theirs = match read_file(&path) {
+                Some(theirs) => theirs,
+                None => continue,
+            };
+            if self.hashes.get(&path) == Some(&calculate_hash(&theirs)) {
+                self.disk.get_mut(&path).unwrap().stamp = current;
+            } else if !self.is_dirty(i) {
+                self.replace_file(i, theirs.clone());
+                self.mark_on_disk(path, theirs, current);
+                messages.push(format!("Reloaded {}", self.files[i]));
+            } else {
+                self.conflicts.push(Conflict {
+                    path,
+                    stamp: current,
+                    theirs,
+                    notes: Vec::new(),
+                });
+            }
+        }
+
+        for i in deleted.into_iter().rev() {
+            let cursor = self.cursor();
+            let (name, path, _) = self.take_file(i);
+            self.hashes.remove(&path);
+            self.disk.remove(&path);
+            messages.push(format!("{} was deleted", name));
+            self.set_cursor(cursor);
+            self.history.remove_file(i);
+        }
+
+        for path in todo_files(self.main_path) {
+            if self.paths.contains(&path) || self.to_remove.contains(&path) {
+                continue;
+            }
+            if let Some(list) = read_file(&path) {
+                let name = file_name(self.main_path, &path);
+                messages.push(format!("Added {}", name));
+                self.files.push(name);
+                self.paths.push(path.clone());
+                self.lists.push(list.clone());
+                self.mark_on_disk(path.clone(), list, stamp(&path));
+            }
+        }
+
+        if !messages.is_empty() {
+            self.message = Some(messages.join(", "));
+        }
+        if !self.conflicts.is_empty() && self.mode == EditorMode::Nothing {
+            self.mode = EditorMode::Conflict;
+        }
+    }
+
+    /// Settle the first queued conflict
+    pub fn resolve_conflict(&mut self, resolution: Resolution) {
+        if self.conflicts.is_empty() {
+            self.mode = EditorMode::Nothing;
+            return;
+        }
+        let mut conflict = self.conflicts.remove(0);
+        if let Some(i) = self.paths.iter().position(|path| *path == conflict.path) {
+            match resolution {
+                Resolution::KeepMine => {}
+                Resolution::TakeTheirs => self.replace_file(i, conflict.theirs.clone()),
+                Resolution::Merge => {
+                    let base = match self.disk.get(&conflict.path) {
+                        Some(file) => &file.base,
+                        None => &conflict.theirs,
+                    };
+                    match merge_files(base, &self.lists[i], &conflict.theirs) {
+                        Ok(merged) => self.replace_file(i, merged),
+                        Err(notes) => {
+                            // Ask again, now only for keeping one side
+                            conflict.notes = notes;
+                            self.conflicts.insert(0, conflict);
+                            return;
+                        }
+                    }
+                }
+            }
+            // Unsaved changes are now relative to the version on disk
+            self.mark_on_disk(conflict.path, conflict.theirs, conflict.stamp);
+        }
+        if self.conflicts.is_empty() {
+            self.mode = EditorMode::Nothing;
+        }
+    }
+
+    /// Remove deleted files and write every changed file, failures do not stop the rest.
+    /// Files changed by other programs are never overwritten before their conflict is resolved.
+    /// Returns the number of files written or removed.
+    pub fn save(&mut self) -> Result<usize, String> {
+        self.check_external_changes();
+        if !self.conflicts.is_empty() {
+            return Err("Files were changed on disk, resolve the conflicts first".to_string());
+        }
+
+        let mut errors: Vec<String> = Vec::new();
+        let mut saved = 0;
+        // Undo may have restored a file which was scheduled for removal
         for file in self.to_remove.iter() {
-            remove_file(file).unwrap();
+            if self.paths.contains(file) || !file.exists() {
+                continue;
+            }
+            match remove_file(file) {
+                Ok(()) => {
+                    self.disk.remove(file);
+                    saved += 1;
+                }
+                Err(err) => errors.push(format!("Could not remove {}: {}", file.display(), err)),
+            }
         }
-        for (i, list) in self.lists.iter().enumerate() {
-            let path = PathBuf::from(self.paths.get(i).unwrap());
-            let stored_hash = self.hashes.get(&path).unwrap_or(&0);
-            let new_hash = calculate_hash(&list);
-            if *stored_hash != new_hash {
-                list.write(&path);
+        for i in 0..self.lists.len() {
+            if !self.is_dirty(i) {
+                continue;
             }
+            let path = self.paths[i].clone();
+            match self.lists[i].write(&path) {
+                Ok(()) => {
+                    let list = self.lists[i].clone();
+                    let stamp = stamp(&path);
+                    self.mark_on_disk(path, list, stamp);
+                    saved += 1;
+                }
+                Err(err) => errors.push(format!("Could not save {}: {}", path.display(), err)),
+            }
+        }
+        if let Err(err) = write_order(self.main_path, self.paths) {
+            errors.push(format!("Could not save the order of files: {}", err));
+        }
+        if errors.is_empty() {
+            Ok(saved)
+        } else {
+            Err(errors.join(", "))
         }
     }
+
+    /// Save and tell how many files were written
+    pub fn save_with_message(&mut self) {
+        self.message = Some(match self.save() {
+            Ok(0) => "Nothing to save".to_string(),
+            Ok(1) => "Saved 1 file".to_string(),
+            Ok(saved) => format!("Saved {} files", saved),
+            Err(err) => err,
+        });
+    }
+
+    /// Names of created or renamed files have to be valid and unused
+    fn check_new_file_name(&self, name: &str) -> Result<(), String> {
+        validate_file_name(name)?;
+        if self.files.iter().any(|file| file == name) {
+            return Err(format!("{} already exists", name));
+        }
+        Ok(())
+    }
     pub fn change(&mut self) {
         self.mode = match self.cursor_vertical {
             0 => {
                 let current_name = self.files.get(self.file_index).unwrap();
-                self.input = current_name.clone();
+                self.input.set(current_name);
                 EditorMode::ChangeFileName
             }
             1 => {
-                let current_list = self.lists.get_mut(self.file_index).unwrap();
-                self.input = current_list
-                    .titles
-                    .get_mut(self.list_index)
-                    .unwrap()
-                    .clone();
+                let current_list = self.lists.get(self.file_index).unwrap();
+                self.input
+                    .set(current_list.titles.get(self.list_index).unwrap());
                 EditorMode::ChangeListName
             }
             2 => {
-                let note = self
-                    .lists
-                    .get_mut(self.file_index)
-                    .unwrap()
-                    .notes
-                    .get_mut(self.list_index)
-                    .unwrap()
-                    .get_mut(self.note_index)
-                    .unwrap();
-                self.input = note.content.clone();
+                let content = self.current_note().unwrap().content.clone();
+                self.input.set(&content);
                 EditorMode::ChangeNoteContent
             }
             _ => EditorMode::Nothing,
         }
     }
     pub fn change_description(&mut self) {
-        let current_list = self.lists.get_mut(self.file_index).unwrap();
-        self.input = current_list
-            .descriptions
-            .get_mut(self.list_index)
-            .unwrap()
-            .clone();
+        let current_list = self.lists.get(self.file_index).unwrap();
+        self.input
+            .set(current_list.descriptions.get(self.list_index).unwrap());
         self.mode = EditorMode::ChangeListDescription;
     }
+    /// Select the journal day of a date, creating it from the template if needed. A new day
+    /// following every other day takes over the open notes of the one before, a day created
+    /// between two others starts from the template only.
+    pub fn open_journal(&mut self, date: NaiveDate) {
+        let name = date.format(DATE_FORMAT).to_string();
+        self.mode = EditorMode::Nothing;
+        if let Some(index) = self.files.iter().position(|file| *file == name) {
+            self.select_file(index);
+            return;
+        }
+        let cursor = self.cursor();
+        let days: Vec<(NaiveDate, usize)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| journal_date(file).map(|day| (day, i)))
+            .collect();
+        let template = match self.find_template("journal") {
+            Ok(Some(template)) => template.content,
+            Ok(None) => self.config.options.journal_template.clone(),
+            Err(err) => {
+                self.message = Some(err);
+                return;
+            }
+        };
+        let content = expand(&template, &name, date);
+        let mut list = parse_lines(content.split('\n').map(String::from).collect());
+        let previous = days.iter().filter(|(day, _)| *day < date).max();
+        if let (Some((_, previous)), false) = (previous, days.iter().any(|(day, _)| *day > date)) {
+            let mut carried = Vec::new();
+            carry_over(&self.lists[*previous], &mut carried);
+            if !carried.is_empty() {
+                if list.titles.is_empty() {
+                    list.push_list(
+                        self.config.options.new_file_title.clone(),
+                        String::new(),
+                        vec![],
+                    );
+                }
+                list.notes[0].extend(carried);
+            }
+        }
+        let index = self.files.len();
+        let path = file_path(self.main_path, &name);
+        self.insert_file(index, name.clone(), path.clone(), list.clone());
+        self.select_file(index);
+        self.record(
+            Change::CreateFile {
+                index,
+                name,
+                path,
+                list,
+            },
+            cursor,
+        );
+    }
+
+    /// Templates of the configured folder
+    pub fn load_templates(&self) -> Result<Vec<Template>, String> {
+        match &self.config.templates_dir {
+            Some(dir) => load_templates(dir),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn find_template(&self, name: &str) -> Result<Option<Template>, String> {
+        Ok(self
+            .load_templates()?
+            .into_iter()
+            .find(|template| template.name == name))
+    }
+
+    /// Offer the templates for the file or list being created, if there are any. Returns
+    /// whether the picker took over the input.
+    pub fn open_templates(&mut self) -> bool {
+        if !matches!(self.mode, EditorMode::CreateFile | EditorMode::CreateList)
+            || self.input.is_empty()
+        {
+            return false;
+        }
+        if self.mode == EditorMode::CreateFile {
+            let input = self.input.text().trim_matches('/').to_string();
+            if self.check_new_file_name(&input).is_err() {
+                return false;
+            }
+        }
+        match self.load_templates() {
+            Ok(templates) if templates.is_empty() => false,
+            Ok(templates) => {
+                self.templates = templates;
+                self.templates_state.select(Some(0));
+                self.template_for = self.mode;
+                self.mode = EditorMode::Template;
+                true
+            }
+            // The file or list is still created, blank
+            Err(err) => {
+                self.message = Some(err);
+                false
+            }
+        }
+    }
+
+    pub fn next_template(&mut self, forward: bool) {
+        let index = self.templates_state.selected().unwrap_or(0);
+        let index = if forward {
+            (index + 1).min(self.templates.len())
+        } else {
+            index.saturating_sub(1)
+        };
+        self.templates_state.select(Some(index));
+    }
+
+    /// Create the file or list from the template chosen in the picker, the first entry is
+    /// the blank one
+    pub fn apply_template(&mut self) {
+        let index = self.templates_state.selected().unwrap_or(0);
+        self.template = index
+            .checked_sub(1)
+            .and_then(|i| self.templates.get(i))
+            .cloned();
+        self.mode = self.template_for;
+        self.handle_enter();
+        self.template = None;
+    }
+
+    /// Go back to the name of the file or list
+    pub fn close_templates(&mut self) {
+        self.mode = self.template_for;
+    }
+
+    pub fn open_today(&mut self) {
+        self.open_journal(Local::now().date_naive());
+    }
+
+    fn select_file(&mut self, index: usize) {
+        self.file_index = index;
+        self.list_index = 0;
+        self.note_index = 0;
+        self.cursor_vertical = 0;
+        self.notes_state.select(None);
+        self.validate_and_update_indices();
+    }
+
+    /// Open today's journal day, or go back to the file selected before the journal
+    pub fn toggle_journal(&mut self) {
+        if !self.in_journal() {
+            self.previous_file = self.files.get(self.file_index).cloned();
+            self.open_today();
+            return;
+        }
+        let index = self
+            .previous_file
+            .as_ref()
+            .and_then(|name| self.files.iter().position(|file| file == name))
+            .filter(|i| journal_date(&self.files[*i]).is_none())
+            .or_else(|| {
+                self.files
+                    .iter()
+                    .position(|file| journal_date(file).is_none())
+            });
+        match index {
+            Some(index) => self.select_file(index),
+            None => self.message = Some("There are no files besides the journal".to_string()),
+        }
+    }
+
+    /// Show the calendar on the selected journal day or today
+    pub fn open_calendar(&mut self) {
+        self.calendar = match self.files.get(self.file_index) {
+            Some(name) => journal_date(name),
+            None => None,
+        }
+        .unwrap_or_else(|| Local::now().date_naive());
+        self.mode = EditorMode::Calendar;
+    }
+
+    pub fn move_calendar(&mut self, days: i64) {
+        self.calendar = self
+            .calendar
+            .checked_add_signed(Duration::days(days))
+            .unwrap_or(self.calendar);
+    }
+
+    pub fn move_calendar_months(&mut self, forward: bool) {
+        let months = Months::new(1);
+        let date = if forward {
+            self.calendar.checked_add_months(months)
+        } else {
+            self.calendar.checked_sub_months(months)
+        };
+        self.calendar = date.unwrap_or(self.calendar);
+    }
+
+    /// Remove right away or ask first if a file or a list with notes would be removed
+    pub fn request_remove(&mut self) {
+        let needs_confirm = match self.cursor_vertical {
+            0 => !self.files.is_empty(),
+            1 => !self.current_notes().is_empty(),
+            _ => false,
+        };
+        if needs_confirm && self.config.options.confirm_remove {
+            self.confirm = Some(Confirm::Remove);
+            self.mode = EditorMode::Confirm;
+        } else {
+            self.remove();
+        }
+    }
+
+    /// Whether the app may quit, otherwise saving or discarding the changes is asked first
+    pub fn request_quit(&mut self) -> bool {
+        if matches!(self.confirm, Some(Confirm::Quit(_))) || !self.has_unsaved_changes() {
+            return true;
+        }
+        // A pending removal is dropped
+        let back = match self.mode {
+            EditorMode::Confirm => EditorMode::Nothing,
+            mode => mode,
+        };
+        self.confirm = Some(Confirm::Quit(back));
+        self.mode = EditorMode::Confirm;
+        false
+    }
+
+    /// Go back to the mode the question was asked in
+    pub fn cancel_confirm(&mut self) {
+        self.mode = match self.confirm.take() {
+            Some(Confirm::Quit(mode)) => mode,
+            _ => EditorMode::Nothing,
+        };
+    }
+
+    pub fn confirm_remove(&mut self) {
+        self.cancel_confirm();
+        self.remove();
+    }
+
     pub fn remove(&mut self) {
         match self.cursor_vertical {
             0 => {
-                self.to_remove
-                    .push(self.paths.get(self.file_index).unwrap().clone());
-                self.paths.remove(self.file_index);
-                self.files.remove(self.file_index);
-                self.lists.remove(self.file_index);
+                if self.files.is_empty() {
+                    return;
+                }
+                let cursor = self.cursor();
+                let index = self.file_index;
+                let (name, path, list) = self.take_file(index);
                 if !self.files.is_empty() && self.file_index >= self.files.len() {
                     self.file_index = self.files.len() - 1
                 } else if self.files.is_empty() {
                     self.file_index = 0;
                     self.cursor_vertical = 0;
                 }
+                self.record(
+                    Change::RemoveFile {
+                        index,
+                        name,
+                        path,
+                        list,
+                    },
+                    cursor,
+                );
             }
-            1 => {
-                self.lists
-                    .get_mut(self.file_index)
-                    .unwrap()
-                    .titles
-                    .remove(self.list_index);
-                self.lists
-                    .get_mut(self.file_index)
-                    .unwrap()
-                    .descriptions
-                    .remove(self.list_index);
-                self.lists
-                    .get_mut(self.file_index)
-                    .unwrap()
-                    .notes
-                    .remove(self.list_index);
-                if !self
-                    .lists
-                    .get_mut(self.file_index)
-                    .unwrap()
-                    .titles
-                    .is_empty()
-                    && self.list_index == self.lists.get_mut(self.file_index).unwrap().titles.len()
-                {
-                    self.list_index = self.lists.get_mut(self.file_index).unwrap().titles.len() - 1;
-                } else if self
-                    .lists
-                    .get_mut(self.file_index)
-                    .unwrap()
-                    .titles
-                    .is_empty()
-                {
-                    self.list_index = 0;
-                    self.cursor_vertical = 0;
-                }
-            }
+            1 => self.edit(|app| app.remove_list()),
             2 => {
-                let notes = self
-                    .lists
-                    .get_mut(self.file_index)
-                    .unwrap()
-                    .notes
-                    .get_mut(self.list_index)
-                    .unwrap();
-                notes.remove(self.note_index);
-                if !notes.is_empty() && self.note_index >= notes.len() {
-                    self.note_index = notes.len() - 1;
-                    self.notes_state.select(Option::from(self.note_index))
-                } else if notes.is_empty() {
-                    self.note_index = 0;
-                    self.cursor_vertical = 1;
-                    self.notes_state.select(Option::from(self.note_index));
-                }
+                let paths = self.selected_paths();
+                self.edit(|app| app.remove_notes(&paths));
             }
             _ => {}
         }
     }
+    fn remove_list(&mut self) {
+        let lists = self.lists.get_mut(self.file_index).unwrap();
+        lists.remove(self.list_index);
+        if !lists.titles.is_empty() && self.list_index == lists.titles.len() {
+            self.list_index = lists.titles.len() - 1;
+        } else if lists.titles.is_empty() {
+            self.list_index = 0;
+            self.cursor_vertical = 0;
+        }
+    }
+    /// Remove notes together with their subtasks
+    fn remove_notes(&mut self, paths: &[Vec<usize>]) {
+        let mut paths = top_level(paths);
+        paths.sort();
+        for path in paths.iter().rev() {
+            remove_note(self.current_notes_mut().unwrap(), path);
+        }
+        self.clear_marks();
+        let size = self.visible_paths().len();
+        if size > 0 && self.note_index >= size {
+            self.note_index = size - 1;
+            self.notes_state.select(Option::from(self.note_index))
+        } else if size == 0 {
+            self.note_index = 0;
+            self.cursor_vertical = 1;
+            self.notes_state.select(Option::from(self.note_index));
+        }
+    }
     pub fn create_file(&mut self) {
         self.mode = EditorMode::CreateFile;
     }
+    pub fn create_folder(&mut self) {
+        self.mode = EditorMode::CreateFolder;
+    }
     pub fn create_list(&mut self) {
         self.mode = EditorMode::CreateList;
     }
@@ -375,104 +1837,271 @@ impl<'a> App<'a> {
         match self.mode {
             EditorMode::CreateFile => {
                 if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    let path = self.main_path.join(format!("{}.md", input));
-                    self.files.push(input);
-                    self.paths.push(path);
-                    let list = FileList {
-                        titles: vec![STARTER_FILE_TITLE.to_string()],
-                        descriptions: vec![STARTER_FILE_DESCRIPTION.to_string()],
-                        notes: vec![vec![Note {
-                            content: STARTER_FILE_NOTE.to_string(),
-                            state: NoteEnum::OPEN,
-                        }]],
+                    let input = self.input.text().trim_matches('/').to_string();
+                    if let Err(err) = self.check_new_file_name(&input) {
+                        self.message = Some(err);
+                        return;
+                    }
+                    let cursor = self.cursor();
+                    let path = file_path(self.main_path, &input);
+                    let list = match self.template.take() {
+                        Some(template) => template.file_list(&input, Local::now().date_naive()),
+                        None => {
+                            let mut list = FileList::default();
+                            list.push_list(
+                                self.config.options.new_file_title.clone(),
+                                self.config.options.new_file_description.clone(),
+                                vec![Note::new(
+                                    self.config.options.new_file_note.clone(),
+                                    NoteEnum::OPEN,
+                                )],
+                            );
+                            list
+                        }
                     };
-                    self.lists.push(list);
-                    self.input = String::new();
+                    let index = self.files.len();
+                    self.insert_file(index, input.clone(), path.clone(), list.clone());
+                    self.record(
+                        Change::CreateFile {
+                            index,
+                            name: input,
+                            path,
+                            list,
+                        },
+                        cursor,
+                    );
+                    self.input.clear();
                     self.mode = EditorMode::Nothing;
                 }
             }
+            EditorMode::CreateFolder => {
+                if !self.input.is_empty() {
+                    let input = self.input.text().trim_matches('/').to_string();
+                    if let Err(err) = validate_file_name(&input) {
+                        self.message = Some(err);
+                        return;
+                    }
+                    let mut path = self.main_path.to_path_buf();
+                    path.extend(input.split('/'));
+                    if let Err(err) = create_dir_all(&path) {
+                        self.message = Some(format!("Could not create {}: {}", input, err));
+                        return;
+                    }
+                    // A folder is only shown once it contains a file
+                    self.input.set(&format!("{}/", input));
+                    self.mode = EditorMode::CreateFile;
+                    return;
+                }
+            }
             EditorMode::CreateList => {
                 if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    let current_list = self.lists.get_mut(self.file_index).unwrap();
-                    current_list.titles.push(input);
-                    current_list.descriptions.push(String::new());
-                    current_list.notes.push(vec![]);
-                    self.input = String::new();
+                    self.edit(|app| {
+                        let input = app.input.text().to_string();
+                        let (description, notes) = match app.template.take() {
+                            Some(template) => template.list(&input, Local::now().date_naive()),
+                            None => (String::new(), vec![]),
+                        };
+                        let current_list = app.lists.get_mut(app.file_index).unwrap();
+                        current_list.push_list(input, description, notes);
+                    });
+                    self.input.clear();
                     self.mode = EditorMode::Nothing;
                 }
             }
             EditorMode::CreateNote => {
                 if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    let current_notes = self
-                        .lists
-                        .get_mut(self.file_index)
-                        .unwrap()
-                        .notes
-                        .get_mut(self.list_index)
-                        .unwrap();
-                    current_notes.push(Note {
-                        content: input,
-                        state: NoteEnum::OPEN,
+                    self.edit(|app| {
+                        let mut input = app.input.text().to_string();
+                        if app.config.options.record_dates {
+                            input = with_created(&input, Local::now().date_naive());
+                        }
+                        let current_notes = app.current_notes_mut().unwrap();
+                        current_notes.push(Note::new(input, NoteEnum::OPEN));
                     });
-                    self.input = String::new();
+                    self.input.clear();
                     self.mode = EditorMode::Nothing;
                 }
             }
             EditorMode::ChangeFileName => {
                 if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    let current_file_name = self.files.get_mut(self.file_index).unwrap();
-                    *current_file_name = input.clone();
-                    let current_path = self.paths.get_mut(self.file_index).unwrap();
-                    remove_file(&current_path).unwrap();
-                    current_path.set_file_name(format!("{}.md", input));
-                    self.input = String::new();
+                    let index = self.file_index;
+                    let input = self.input.text().trim_matches('/').to_string();
+                    if input == self.files[index] {
+                        self.input.clear();
+                        self.mode = EditorMode::Nothing;
+                        return;
+                    }
+                    if let Err(err) = self.check_new_file_name(&input) {
+                        self.message = Some(err);
+                        return;
+                    }
+                    let cursor = self.cursor();
+                    let before = (self.files[index].clone(), self.paths[index].clone());
+                    let path = file_path(self.main_path, &input);
+                    self.rename_file(index, input.clone(), path.clone());
+                    self.record(
+                        Change::RenameFile {
+                            index,
+                            before,
+                            after: (input, path),
+                        },
+                        cursor,
+                    );
+                    self.input.clear();
                     self.mode = EditorMode::Nothing;
                 }
             }
             EditorMode::ChangeListName => {
                 if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    let current_list = self.lists.get_mut(self.file_index).unwrap();
-                    *current_list.titles.get_mut(self.list_index).unwrap() = input;
-                    self.input = String::new();
+                    self.edit(|app| {
+                        let input = app.input.text().to_string();
+                        let current_list = app.lists.get_mut(app.file_index).unwrap();
+                        *current_list.titles.get_mut(app.list_index).unwrap() = input;
+                    });
+                    self.input.clear();
                     self.mode = EditorMode::Nothing;
                 }
             }
             EditorMode::ChangeListDescription => {
-                if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    let current_list = self.lists.get_mut(self.file_index).unwrap();
-                    *current_list.descriptions.get_mut(self.list_index).unwrap() = input;
-                    self.input = String::new();
+                if !self.input.text().trim().is_empty() {
+                    self.edit(|app| {
+                        // Blank lines around the description belong to the layout
+                        let input = trim_blank_lines(app.input.text());
+                        let current_list = app.lists.get_mut(app.file_index).unwrap();
+                        let description =
+                            current_list.descriptions.get_mut(app.list_index).unwrap();
+                        let was_empty = description.is_empty();
+                        *description = input;
+                        if let Some(layout) = current_list.layouts.get_mut(app.list_index) {
+                            if was_empty && layout.after_description.is_empty() {
+                                layout.after_description.push(String::new());
+                            }
+                        }
+                    });
+                    self.input.clear();
                     self.mode = EditorMode::Nothing;
                 }
             }
             EditorMode::ChangeNoteContent => {
                 if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    let note = self
-                        .lists
-                        .get_mut(self.file_index)
-                        .unwrap()
-                        .notes
-                        .get_mut(self.list_index)
-                        .unwrap()
-                        .get_mut(self.note_index)
-                        .unwrap();
-                    *note = Note {
-                        content: input,
-                        state: note.state.clone(),
-                    };
-                    self.input = String::new();
+                    self.edit(|app| {
+                        let input = app.input.text().to_string();
+                        let note = app.current_note_mut().unwrap();
+                        note.set_content(input);
+                    });
+                    self.input.clear();
                     self.mode = EditorMode::Nothing;
                 }
             }
+            EditorMode::BulkTag => {
+                let input = self.input.text().to_string();
+                self.apply_tags(&input);
+                self.mode = self.normal_mode();
+            }
             _ => {}
         }
-        self.input = String::new();
+        self.input.clear();
+    }
+
+    /// Close the input box without applying it
+    pub fn cancel_input(&mut self) {
+        self.mode = match self.mode {
+            EditorMode::BulkTag => self.normal_mode(),
+            _ => EditorMode::Nothing,
+        };
+        self.input.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::{parse_lines, NoteEnum};
+    use crate::ui::app::App;
+    use crate::util::calculate_hash;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn lines(content: &str) -> Vec<String> {
+        content.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn reload_test() {
+        let dir = std::env::temp_dir().join(format!("yoku-reload-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut files = vec!["a".to_string(), "b".to_string()];
+        let mut paths = vec![dir.join("a.md"), dir.join("b.md")];
+        let mut lists = Vec::new();
+        let mut hashes = HashMap::new();
+        for path in paths.iter() {
+            fs::write(path, "# Todo\n- [ ] task\n").unwrap();
+            let list = parse_lines(lines("# Todo\n- [ ] task\n"));
+            hashes.insert(path.clone(), calculate_hash(&list));
+            lists.push(list);
+        }
+        let mut to_remove = Vec::new();
+        let mut app = App::new(
+            &mut files,
+            &mut paths,
+            &mut lists,
+            &mut hashes,
+            &dir,
+            &mut to_remove,
+        );
+        app.file_index = 0;
+        app.list_index = 0;
+        app.note_index = 0;
+        app.cursor_vertical = 2;
+        app.set_note_state(NoteEnum::DONE);
+        assert_eq!(app.lists[0].notes[0][0].state, NoteEnum::DONE);
+
+        fs::write(dir.join("b.md"), "# Todo\n- [ ] task\n- [ ] new\n").unwrap();
+        app.check_external_changes();
+        assert_eq!(app.lists[1].notes[0].len(), 2);
+        app.undo();
+        assert_eq!(app.lists[0].notes[0][0].state, NoteEnum::OPEN);
+        assert_eq!(app.lists[1].notes[0].len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit_externally_test() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("yoku-editor-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let editor = dir.join("editor.sh");
+        fs::write(
+            &editor,
+            "#!/bin/sh\nprintf 'intro\\n- [ ] changed\\n  - [ ] child\\n' > \"$1\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+        std::env::set_var("VISUAL", &editor);
+
+        let mut files = vec!["a".to_string()];
+        let mut paths = vec![dir.join("a.md")];
+        let mut lists = vec![parse_lines(lines("# Todo\n- [ ] task\n"))];
+        let mut hashes = HashMap::new();
+        let mut to_remove = Vec::new();
+        let mut app = App::new(
+            &mut files,
+            &mut paths,
+            &mut lists,
+            &mut hashes,
+            &dir,
+            &mut to_remove,
+        );
+        app.file_index = 0;
+        app.cursor_vertical = 2;
+        app.edit_externally();
+        assert_eq!(
+            app.lists[0].to_string(),
+            "# Todo\n- [ ] changed\n  - [ ] child\n"
+        );
+        assert!(app.message.is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,285 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Text with a cursor, as used by the input box. Only inputs for multi-line text insert line
+/// breaks, the other ones stay a single line. Bracketed paste is not supported by crossterm
+/// 0.23, pasted text arrives as single key presses.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset of the cursor, always on a char boundary
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replace the text, placing the cursor at its end
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Number of lines, at least one
+    pub fn line_count(&self) -> usize {
+        self.text.split('\n').count()
+    }
+
+    /// Start of the line the cursor is on
+    fn line_start(&self) -> usize {
+        self.text[..self.cursor].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// End of the line the cursor is on, before its line break
+    fn line_end(&self) -> usize {
+        self.text[self.cursor..]
+            .find('\n')
+            .map_or(self.text.len(), |i| self.cursor + i)
+    }
+
+    /// Byte offset of the char at `column` chars into the line starting at `start`, or the
+    /// end of that line if it is shorter
+    fn offset_in_line(&self, start: usize, column: usize) -> usize {
+        let line = self.text[start..].split('\n').next().unwrap_or("");
+        line.char_indices()
+            .nth(column)
+            .map_or(start + line.len(), |(i, _)| start + i)
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Start of the word before the cursor, words are separated by whitespace
+    fn word_start(&self) -> usize {
+        let before = &self.text[..self.cursor];
+        let trimmed = before.trim_end();
+        trimmed
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// End of the word after the cursor
+    fn word_end(&self) -> usize {
+        let after = &self.text[self.cursor..];
+        let skipped = after.len() - after.trim_start().len();
+        after[skipped..]
+            .char_indices()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(self.text.len(), |(i, _)| self.cursor + skipped + i)
+    }
+
+    pub fn backspace(&mut self) {
+        let start = self.previous_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.previous_boundary();
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = self.line_start();
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.line_end();
+    }
+
+    /// Move to the same column of the previous line
+    pub fn up(&mut self) {
+        let start = self.line_start();
+        if start == 0 {
+            return;
+        }
+        let column = self.text[start..self.cursor].chars().count();
+        let previous = self.text[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        self.cursor = self.offset_in_line(previous, column);
+    }
+
+    /// Move to the same column of the next line
+    pub fn down(&mut self) {
+        let end = self.line_end();
+        if end == self.text.len() {
+            return;
+        }
+        let column = self.text[self.line_start()..self.cursor].chars().count();
+        self.cursor = self.offset_in_line(end + 1, column);
+    }
+
+    pub fn word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    pub fn word_right(&mut self) {
+        self.cursor = self.word_end();
+    }
+
+    pub fn delete_word(&mut self) {
+        let start = self.word_start();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete_to_start(&mut self) {
+        let start = self.line_start();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete_to_end(&mut self) {
+        let end = self.line_end();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Apply an editing key, returns false for keys which are not handled
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char('w') if control => self.delete_word(),
+            KeyCode::Char('u') if control => self.delete_to_start(),
+            KeyCode::Char('k') if control => self.delete_to_end(),
+            KeyCode::Char('a') if control => self.home(),
+            KeyCode::Char('e') if control => self.end(),
+            KeyCode::Char('b') if alt => self.word_left(),
+            KeyCode::Char('f') if alt => self.word_right(),
+            KeyCode::Char(c) if !control && !alt => self.insert(c),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left if control => self.word_left(),
+            KeyCode::Right if control => self.word_right(),
+            KeyCode::Left => self.left(),
+            KeyCode::Right => self.right(),
+            KeyCode::Up => self.up(),
+            KeyCode::Down => self.down(),
+            KeyCode::Home => self.home(),
+            KeyCode::End => self.end(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Part of the text shown in a box of the given width and the column of the cursor in
+    /// it. The text scrolls horizontally to keep the cursor visible.
+    pub fn view(&self, width: usize) -> (&str, usize) {
+        let line_start = self.line_start();
+        let mut start = line_start;
+        let mut column = self.text[line_start..self.cursor].width();
+        for c in self.text[line_start..self.cursor].chars() {
+            if column < width.max(1) {
+                break;
+            }
+            start += c.len_utf8();
+            column -= c.width().unwrap_or(0);
+        }
+        (&self.text[start..self.line_end()], column)
+    }
+
+    /// Lines shown in a box of the given size and the column and row of the cursor in it.
+    /// The text scrolls vertically to keep the cursor line visible, which alone scrolls
+    /// horizontally as in `view`.
+    pub fn view_lines(&self, width: usize, height: usize) -> (Vec<&str>, usize, usize) {
+        let row = self.text[..self.cursor].matches('\n').count();
+        let first = (row + 1).saturating_sub(height.max(1));
+        let (cursor_line, column) = self.view(width);
+        let lines = self
+            .text
+            .split('\n')
+            .enumerate()
+            .skip(first)
+            .take(height.max(1))
+            .map(|(i, line)| if i == row { cursor_line } else { line })
+            .collect();
+        (lines, column, row - first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::editor::LineEditor;
+
+    #[test]
+    fn editor_test() {
+        let mut editor = LineEditor::default();
+        editor.set("buy milk");
+        editor.word_left();
+        "oat ".chars().for_each(|c| editor.insert(c));
+        assert_eq!(editor.text(), "buy oat milk");
+        editor.home();
+        editor.word_right();
+        editor.delete();
+        editor.insert('-');
+        assert_eq!(editor.text(), "buy-oat milk");
+        editor.end();
+        editor.delete_word();
+        assert_eq!(editor.text(), "buy-oat ");
+        editor.left();
+        editor.delete_to_start();
+        assert_eq!((editor.text(), editor.cursor()), (" ", 0));
+
+        editor.set("日本語 text");
+        editor.word_left();
+        editor.backspace();
+        assert_eq!(editor.text(), "日本語text");
+        assert_eq!(editor.view(4), ("語text", 2));
+        assert_eq!(editor.view(20), ("日本語text", 6));
+
+        editor.set("first\nsecond line\nthird");
+        editor.up();
+        assert_eq!(editor.cursor(), "first\nsecon".len());
+        editor.up();
+        editor.end();
+        editor.down();
+        editor.insert('!');
+        assert_eq!(editor.text(), "first\nsecon!d line\nthird");
+        editor.delete_to_end();
+        editor.down();
+        editor.insert('\n');
+        assert_eq!(editor.text(), "first\nsecon!\nthird\n");
+        assert_eq!(editor.line_count(), 4);
+        assert_eq!(editor.view_lines(3, 2), (vec!["third", ""], 0, 1));
+    }
+}
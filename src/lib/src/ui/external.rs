@@ -0,0 +1,105 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Editor command of the user, `$VISUAL` is preferred over `$EDITOR`
+pub fn editor() -> String {
+    env::var("VISUAL")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .or_else(|| env::var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Folder only the user can access, removed with everything in it when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creating fails on an existing folder or link, so a name guessed by someone else in the
+    /// shared temporary directory is skipped rather than used
+    fn new() -> Result<TempDir, String> {
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.subsec_nanos());
+        for attempt in 0..100 {
+            let path =
+                env::temp_dir().join(format!("yoku-{}-{}-{}", process::id(), nanos, attempt));
+            match builder.create(&path) {
+                Ok(()) => return Ok(TempDir(path)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(format!("Could not create {}: {}", path.display(), err)),
+            }
+        }
+        Err("Could not create a temporary folder".to_string())
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Open the content as a temporary Markdown file in the editor and return it once the
+/// editor exits. The terminal has to be handed over to the editor beforehand.
+pub fn edit(content: &str, name: &str) -> Result<String, String> {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let dir = TempDir::new()?;
+    let path = dir.0.join(format!("{}.md", name));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|err| format!("Could not write {}: {}", path.display(), err))?;
+
+    let editor = editor();
+    // Allow arguments such as `code --wait`
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(DEFAULT_EDITOR);
+    let res = match Command::new(program).args(words).arg(&path).status() {
+        Ok(status) if status.success() => fs::read_to_string(&path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err)),
+        Ok(status) => Err(format!("{} exited with {}", editor, status)),
+        Err(err) => Err(format!("Could not run {}: {}", editor, err)),
+    };
+    drop(dir);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::external::TempDir;
+
+    #[test]
+    fn temp_dir_test() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.0.clone();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        let other = TempDir::new().unwrap();
+        assert_ne!(other.0, path);
+        std::fs::write(path.join("note.md"), "- [ ] a\n").unwrap();
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::config::Config;
+use crate::ui::app::EditorMode;
+use crate::ui::keymap::{
+    Action, KeyBinding, Keymap, CALENDAR_KEYS, DIALOG_KEYS, NORMAL_KEYS, VISUAL_KEYS,
+};
+
+/// Keys of the line editor, shared by every mode with an input box
+const INPUT_KEYS: &[(&str, &str)] = &[
+    ("Enter", "confirm"),
+    ("Esc", "cancel"),
+    ("Left, Right", "move the cursor"),
+    ("Ctrl+Left, Alt+b", "word left"),
+    ("Ctrl+Right, Alt+f", "word right"),
+    ("Home, Ctrl+a", "start of the line"),
+    ("End, Ctrl+e", "end of the line"),
+    ("Ctrl+w", "delete the word before the cursor"),
+    ("Ctrl+u", "delete to the start of the line"),
+    ("Ctrl+k", "delete to the end of the line"),
+];
+
+fn join(keys: &[KeyBinding]) -> String {
+    keys.iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Bound keys of the actions in the order of the defaults, unbound actions are left out
+fn from_keymap(
+    keymap: &Keymap,
+    defaults: &[(Action, &[&str])],
+    include: &dyn Fn(Action) -> bool,
+) -> Vec<(String, String)> {
+    defaults
+        .iter()
+        .map(|(action, _)| *action)
+        .filter(|action| include(*action))
+        .filter_map(|action| {
+            let keys = keymap.keys(action);
+            if keys.is_empty() {
+                None
+            } else {
+                Some((join(&keys), action.description().to_string()))
+            }
+        })
+        .collect()
+}
+
+fn static_keys(keys: &[(&str, &str)]) -> Vec<(String, String)> {
+    keys.iter()
+        .map(|(keys, description)| (keys.to_string(), description.to_string()))
+        .collect()
+}
+
+/// Keys and what they do in a mode. In normal mode, actions on notes are only listed while
+/// the cursor is on the notes.
+pub fn help_entries(
+    mode: EditorMode,
+    cursor_vertical: usize,
+    config: &Config,
+) -> Vec<(String, String)> {
+    let up_down = || {
+        let mut keys = config.keys.keys(Action::Up);
+        keys.extend(config.keys.keys(Action::Down));
+        (join(&keys), "previous or next".to_string())
+    };
+    let dialog = |actions: &[Action], description: &str| {
+        (config.dialog_keys.names(actions), description.to_string())
+    };
+    match mode {
+        EditorMode::Nothing => from_keymap(&config.keys, NORMAL_KEYS, &|action| {
+            cursor_vertical == 2 || !action.needs_note()
+        }),
+        EditorMode::Visual => {
+            let mut entries = from_keymap(&config.visual_keys, VISUAL_KEYS, &|action| {
+                action != Action::Back
+            });
+            let back = config.visual_keys.keys(Action::Back);
+            if !back.is_empty() {
+                entries.push((join(&back), "leave visual mode".to_string()));
+            }
+            entries
+        }
+        EditorMode::SearchResults => {
+            let mut close = config.dialog_keys.keys(Action::Back);
+            close.extend(config.keys.keys(Action::SearchResults));
+            vec![
+                up_down(),
+                dialog(&[Action::Select], "jump to the match"),
+                (join(&close), "close".to_string()),
+            ]
+        }
+        EditorMode::SendTo => {
+            vec![
+                up_down(),
+                dialog(&[Action::Select], "move or copy there"),
+                dialog(&[Action::Back], "cancel"),
+            ]
+        }
+        EditorMode::Template => {
+            vec![
+                up_down(),
+                dialog(&[Action::Select], "create from the template"),
+                dialog(&[Action::Back], "back to the name"),
+            ]
+        }
+        EditorMode::Calendar => {
+            let mut entries = from_keymap(&config.keys, NORMAL_KEYS, &|action| {
+                matches!(
+                    action,
+                    Action::Up | Action::Down | Action::Left | Action::Right
+                )
+            });
+            for (_, description) in entries.iter_mut() {
+                *description = match description.as_str() {
+                    "up" => "previous week",
+                    "down" => "next week",
+                    "previous file or list" => "previous day",
+                    _ => "next day",
+                }
+                .to_string();
+            }
+            entries.extend(from_keymap(&config.calendar_keys, CALENDAR_KEYS, &|_| true));
+            entries.push(dialog(&[Action::Select], "open or create the journal day"));
+            entries.push(dialog(&[Action::Back], "close"));
+            entries
+        }
+        EditorMode::Confirm => {
+            let mut entries = from_keymap(&config.dialog_keys, DIALOG_KEYS, &|action| {
+                matches!(
+                    action,
+                    Action::SaveAndQuit | Action::Discard | Action::Confirm
+                )
+            });
+            entries.push(dialog(&[Action::Back, Action::Cancel], "cancel"));
+            entries
+        }
+        EditorMode::Conflict => from_keymap(&config.dialog_keys, DIALOG_KEYS, &|action| {
+            matches!(
+                action,
+                Action::KeepMine | Action::TakeTheirs | Action::Merge
+            )
+        }),
+        _ => {
+            let mut entries = static_keys(INPUT_KEYS);
+            if mode.is_multiline() {
+                entries.insert(2, ("Alt+Enter, Ctrl+j".to_string(), "new line".to_string()));
+                entries.insert(
+                    3,
+                    ("Up, Down".to_string(), "previous or next line".to_string()),
+                );
+            }
+            if mode == EditorMode::Search {
+                let toggle = from_keymap(&config.dialog_keys, DIALOG_KEYS, &|action| {
+                    action == Action::ToggleRegex
+                });
+                entries.splice(2..2, toggle);
+            }
+            entries
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::ui::app::EditorMode;
+    use crate::ui::help::help_entries;
+
+    #[test]
+    fn help_test() {
+        let config = Config::default();
+        let has = |entries: &[(String, String)], keys: &str| entries.iter().any(|(k, _)| k == keys);
+        let files = help_entries(EditorMode::Nothing, 0, &config);
+        let notes = help_entries(EditorMode::Nothing, 2, &config);
+        assert!(has(&files, "F1, ?"));
+        assert!(!has(&files, "+, x"));
+        assert!(has(&notes, "+, x"));
+        assert!(notes.len() > files.len());
+
+        let description = help_entries(EditorMode::ChangeListDescription, 1, &config);
+        assert!(has(&description, "Alt+Enter, Ctrl+j"));
+        assert!(!has(
+            &help_entries(EditorMode::CreateNote, 2, &config),
+            "Alt+Enter, Ctrl+j"
+        ));
+        assert!(has(&help_entries(EditorMode::Search, 2, &config), "Ctrl+r"));
+        assert!(has(
+            &help_entries(EditorMode::Confirm, 2, &config),
+            "Esc, c, n"
+        ));
+        assert!(has(
+            &help_entries(EditorMode::Calendar, 2, &config),
+            "PageUp, ["
+        ));
+        assert!(has(
+            &help_entries(EditorMode::SearchResults, 2, &config),
+            "Esc, f"
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::todo::FileList;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// Number of changes which can be undone
+pub const HISTORY_SIZE: usize = 100;
+
+/// Position of all cursors, restored together with a change
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Cursor {
+    pub vertical: usize,
+    pub file_index: usize,
+    pub list_index: usize,
+    pub note_index: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum Change {
+    /// Lists, descriptions or notes of a file were modified
+    Edit {
+        file_index: usize,
+        before: FileList,
+        after: FileList,
+    },
+    /// Changes made together, such as moving a note to another file
+    Group(Vec<Change>),
+    CreateFile {
+        index: usize,
+        name: String,
+        path: PathBuf,
+        list: FileList,
+    },
+    /// The file is deleted once the app saves
+    RemoveFile {
+        index: usize,
+        name: String,
+        path: PathBuf,
+        list: FileList,
+    },
+    /// Two neighbouring files changed places
+    SwapFiles { index: usize, other: usize },
+    /// The old file is deleted once the app saves
+    RenameFile {
+        index: usize,
+        before: (String, PathBuf),
+        after: (String, PathBuf),
+    },
+}
+
+impl Change {
+    /// Whether the change involves the file at `index`
+    fn touches(&self, index: usize) -> bool {
+        match self {
+            Change::Edit { file_index, .. } => *file_index == index,
+            Change::Group(changes) => changes.iter().any(|change| change.touches(index)),
+            Change::CreateFile { index: i, .. }
+            | Change::RemoveFile { index: i, .. }
+            | Change::RenameFile { index: i, .. } => *i == index,
+            Change::SwapFiles { index: i, other } => *i == index || *other == index,
+        }
+    }
+
+    /// Whether files are at other positions before the change than after it
+    fn moves_files(&self) -> bool {
+        match self {
+            Change::Group(changes) => changes.iter().any(Change::moves_files),
+            Change::CreateFile { .. } | Change::RemoveFile { .. } | Change::SwapFiles { .. } => {
+                true
+            }
+            Change::Edit { .. } | Change::RenameFile { .. } => false,
+        }
+    }
+
+    /// Refer to the files after `removed` by their new position
+    fn file_removed(&mut self, removed: usize) {
+        let shift = |index: &mut usize| {
+            if *index > removed {
+                *index -= 1;
+            }
+        };
+        match self {
+            Change::Edit { file_index, .. } => shift(file_index),
+            Change::Group(changes) => changes
+                .iter_mut()
+                .for_each(|change| change.file_removed(removed)),
+            Change::CreateFile { index, .. }
+            | Change::RemoveFile { index, .. }
+            | Change::RenameFile { index, .. } => shift(index),
+            Change::SwapFiles { index, other } => {
+                shift(index);
+                shift(other);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub change: Change,
+    pub before: Cursor,
+    pub after: Cursor,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl History {
+    /// Record a new change, dropping everything which could be redone
+    pub fn push(&mut self, entry: Entry) {
+        self.redo.clear();
+        self.push_undo(entry);
+    }
+
+    fn push_undo(&mut self, entry: Entry) {
+        self.undo.push_back(entry);
+        if self.undo.len() > HISTORY_SIZE {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Take the latest change, it has to be passed to `undone` once reverted
+    pub fn undo(&mut self) -> Option<Entry> {
+        self.undo.pop_back()
+    }
+
+    pub fn undone(&mut self, entry: Entry) {
+        self.redo.push(entry);
+    }
+
+    /// Take the latest reverted change, it has to be passed to `redone` once applied
+    pub fn redo(&mut self) -> Option<Entry> {
+        self.redo.pop()
+    }
+
+    pub fn redone(&mut self, entry: Entry) {
+        self.push_undo(entry);
+    }
+
+    /// Forget the changes of a file which was replaced by its version on disk, undoing them
+    /// would bring back the content which was replaced. Changes made before one which moved
+    /// the file refer to files by their old positions, so they are forgotten as well.
+    pub fn forget_file(&mut self, index: usize) {
+        self.redo.clear();
+        let moved = self
+            .undo
+            .iter()
+            .rposition(|entry| entry.change.touches(index) && entry.change.moves_files());
+        if let Some(moved) = moved {
+            self.undo.drain(..=moved);
+        }
+        self.undo.retain(|entry| !entry.change.touches(index));
+    }
+
+    /// Forget the changes of a file which is gone and renumber the files after it. Only the
+    /// changes since files were last moved know their current positions, older ones are
+    /// forgotten as well.
+    pub fn remove_file(&mut self, index: usize) {
+        self.redo.clear();
+        if let Some(moved) = self
+            .undo
+            .iter()
+            .rposition(|entry| entry.change.moves_files())
+        {
+            self.undo.drain(..=moved);
+        }
+        self.undo.retain(|entry| !entry.change.touches(index));
+        for entry in self.undo.iter_mut() {
+            entry.change.file_removed(index);
+            for cursor in [&mut entry.before, &mut entry.after] {
+                if cursor.file_index > index {
+                    cursor.file_index -= 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::FileList;
+    use crate::ui::history::{Change, Cursor, Entry, History, HISTORY_SIZE};
+
+    fn entry(file_index: usize) -> Entry {
+        Entry {
+            change: Change::Edit {
+                file_index,
+                before: FileList::default(),
+                after: FileList::default(),
+            },
+            before: Cursor::default(),
+            after: Cursor::default(),
+        }
+    }
+
+    #[test]
+    fn history_test() {
+        let mut history = History::default();
+        for i in 0..HISTORY_SIZE + 5 {
+            history.push(entry(i));
+        }
+        let mut undone = 0;
+        while let Some(entry) = history.undo() {
+            history.undone(entry);
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_SIZE);
+
+        let entry = history.redo().unwrap();
+        assert!(matches!(entry.change, Change::Edit { file_index: 5, .. }));
+        history.redone(entry);
+        history.push(self::entry(0));
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn forget_file_test() {
+        let swap = |index, other| Entry {
+            change: Change::SwapFiles { index, other },
+            ..entry(0)
+        };
+        let mut history = History::default();
+        for change in [entry(0), swap(2, 3), entry(1), entry(2), entry(3)] {
+            history.push(change);
+        }
+        history.forget_file(1);
+        let files: Vec<usize> = std::iter::from_fn(|| history.undo())
+            .map(|entry| match entry.change {
+                Change::Edit { file_index, .. } => file_index,
+                _ => 9,
+            })
+            .collect();
+        assert_eq!(files, vec![3, 2, 9, 0]);
+
+        for change in [entry(0), swap(2, 3), entry(1), entry(2), entry(3)] {
+            history.push(change);
+        }
+        history.forget_file(3);
+        assert_eq!(history.undo.len(), 2);
+
+        let mut history = History::default();
+        for change in [swap(0, 1), entry(0), entry(1), entry(2)] {
+            history.push(change);
+        }
+        history.remove_file(1);
+        let files: Vec<usize> = std::iter::from_fn(|| history.undo())
+            .map(|entry| match entry.change {
+                Change::Edit { file_index, .. } => file_index,
+                _ => 9,
+            })
+            .collect();
+        assert_eq!(files, vec![1, 0]);
+    }
+}
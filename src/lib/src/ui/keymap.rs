@@ -0,0 +1,642 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Everything a key can be bound to, in normal or visual mode, in dialogs or in the calendar
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    CreateNote,
+    CreateFile,
+    CreateFolder,
+    CreateList,
+    Undo,
+    Redo,
+    Remove,
+    Edit,
+    EditDescription,
+    EditExternally,
+    Save,
+    SaveAndQuit,
+    Quit,
+    Help,
+    MoveUp,
+    MoveDown,
+    Up,
+    Down,
+    Left,
+    Right,
+    Back,
+    Visual,
+    Yank,
+    Cut,
+    Paste,
+    MoveTo,
+    CopyTo,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    SearchResults,
+    CycleState,
+    Open,
+    InProgress,
+    Defer,
+    Block,
+    Done,
+    Reject,
+    Indent,
+    Outdent,
+    Collapse,
+    SortByPriority,
+    SortByDue,
+    Mark,
+    MarkRange,
+    MarkAll,
+    MarkOpen,
+    MarkDone,
+    MarkRejected,
+    ClearMarks,
+    PriorityHigh,
+    PriorityMedium,
+    PriorityLow,
+    PriorityNone,
+    Tag,
+    NextTheme,
+    Journal,
+    Calendar,
+    Select,
+    Cancel,
+    Discard,
+    Confirm,
+    KeepMine,
+    TakeTheirs,
+    Merge,
+    ToggleRegex,
+    PreviousMonth,
+    NextMonth,
+    Today,
+}
+
+/// Names of actions as used in the config file
+const ACTION_NAMES: &[(Action, &str)] = &[
+    (Action::CreateNote, "create_note"),
+    (Action::CreateFile, "create_file"),
+    (Action::CreateFolder, "create_folder"),
+    (Action::CreateList, "create_list"),
+    (Action::Undo, "undo"),
+    (Action::Redo, "redo"),
+    (Action::Remove, "remove"),
+    (Action::Edit, "edit"),
+    (Action::EditDescription, "edit_description"),
+    (Action::EditExternally, "edit_externally"),
+    (Action::Save, "save"),
+    (Action::SaveAndQuit, "save_and_quit"),
+    (Action::Quit, "quit"),
+    (Action::Help, "help"),
+    (Action::MoveUp, "move_up"),
+    (Action::MoveDown, "move_down"),
+    (Action::Up, "up"),
+    (Action::Down, "down"),
+    (Action::Left, "left"),
+    (Action::Right, "right"),
+    (Action::Back, "back"),
+    (Action::Visual, "visual"),
+    (Action::Yank, "yank"),
+    (Action::Cut, "cut"),
+    (Action::Paste, "paste"),
+    (Action::MoveTo, "move_to"),
+    (Action::CopyTo, "copy_to"),
+    (Action::Search, "search"),
+    (Action::NextMatch, "next_match"),
+    (Action::PreviousMatch, "previous_match"),
+    (Action::SearchResults, "search_results"),
+    (Action::CycleState, "cycle_state"),
+    (Action::Open, "open"),
+    (Action::InProgress, "in_progress"),
+    (Action::Defer, "defer"),
+    (Action::Block, "block"),
+    (Action::Done, "done"),
+    (Action::Reject, "reject"),
+    (Action::Indent, "indent"),
+    (Action::Outdent, "outdent"),
+    (Action::Collapse, "collapse"),
+    (Action::SortByPriority, "sort_by_priority"),
+    (Action::SortByDue, "sort_by_due"),
+    (Action::Mark, "mark"),
+    (Action::MarkRange, "mark_range"),
+    (Action::MarkAll, "mark_all"),
+    (Action::MarkOpen, "mark_open"),
+    (Action::MarkDone, "mark_done"),
+    (Action::MarkRejected, "mark_rejected"),
+    (Action::ClearMarks, "clear_marks"),
+    (Action::PriorityHigh, "priority_high"),
+    (Action::PriorityMedium, "priority_medium"),
+    (Action::PriorityLow, "priority_low"),
+    (Action::PriorityNone, "priority_none"),
+    (Action::Tag, "tag"),
+    (Action::NextTheme, "next_theme"),
+    (Action::Journal, "journal"),
+    (Action::Calendar, "calendar"),
+    (Action::Select, "select"),
+    (Action::Cancel, "cancel"),
+    (Action::Discard, "discard"),
+    (Action::Confirm, "confirm"),
+    (Action::KeepMine, "keep_mine"),
+    (Action::TakeTheirs, "take_theirs"),
+    (Action::Merge, "merge"),
+    (Action::ToggleRegex, "toggle_regex"),
+    (Action::PreviousMonth, "previous_month"),
+    (Action::NextMonth, "next_month"),
+    (Action::Today, "today"),
+];
+
+/// Default keys of normal mode
+pub const NORMAL_KEYS: &[(Action, &[&str])] = &[
+    (Action::CreateNote, &["o"]),
+    (Action::CreateFile, &["u"]),
+    (Action::CreateFolder, &["U"]),
+    (Action::CreateList, &["i"]),
+    (Action::Undo, &["Ctrl+z"]),
+    (Action::Redo, &["Ctrl+y", "Ctrl+r"]),
+    (Action::Remove, &["r"]),
+    (Action::Edit, &["e"]),
+    (Action::EditDescription, &["Ctrl+e"]),
+    (Action::EditExternally, &["E"]),
+    (Action::Save, &["Ctrl+s"]),
+    (Action::SaveAndQuit, &["q"]),
+    (Action::Quit, &["Ctrl+q"]),
+    (Action::Help, &["?", "F1"]),
+    (Action::MoveUp, &["Shift+Up", "K"]),
+    (Action::MoveDown, &["Shift+Down", "J"]),
+    (Action::Up, &["Up", "w", "k"]),
+    (Action::Down, &["Down", "s", "j"]),
+    (Action::Left, &["Left", "a", "h"]),
+    (Action::Right, &["Right", "d", "l"]),
+    (Action::Back, &["Esc"]),
+    (Action::Visual, &["v"]),
+    (Action::Yank, &["y"]),
+    (Action::Cut, &["X"]),
+    (Action::Paste, &["p"]),
+    (Action::MoveTo, &["m"]),
+    (Action::CopyTo, &["M"]),
+    (Action::Search, &["/"]),
+    (Action::NextMatch, &["n"]),
+    (Action::PreviousMatch, &["N"]),
+    (Action::SearchResults, &["f"]),
+    (Action::CycleState, &["Enter", "Space"]),
+    // Unbound unless configured
+    (Action::Open, &[]),
+    (Action::InProgress, &["t"]),
+    (Action::Defer, &[">"]),
+    (Action::Block, &["!"]),
+    (Action::Done, &["x", "+"]),
+    (Action::Reject, &["-"]),
+    (Action::Indent, &["Tab"]),
+    (Action::Outdent, &["BackTab"]),
+    (Action::Collapse, &["z"]),
+    (Action::SortByPriority, &["S"]),
+    (Action::SortByDue, &["D"]),
+    (Action::NextTheme, &["T"]),
+    (Action::Journal, &["c"]),
+    (Action::Calendar, &["C"]),
+];
+
+/// Default keys of visual mode
+pub const VISUAL_KEYS: &[(Action, &[&str])] = &[
+    (Action::Up, &["Up", "w", "k"]),
+    (Action::Down, &["Down", "s", "j"]),
+    (Action::Mark, &["v", "Space"]),
+    (Action::MarkRange, &["V"]),
+    (Action::MarkAll, &["a"]),
+    (Action::MarkOpen, &["O"]),
+    (Action::MarkDone, &["D"]),
+    (Action::MarkRejected, &["R"]),
+    (Action::ClearMarks, &["n"]),
+    (Action::Open, &["o"]),
+    (Action::InProgress, &["t"]),
+    (Action::Defer, &[">"]),
+    (Action::Block, &["!"]),
+    (Action::Done, &["x", "+"]),
+    (Action::Reject, &["-"]),
+    (Action::PriorityHigh, &["1"]),
+    (Action::PriorityMedium, &["2"]),
+    (Action::PriorityLow, &["3"]),
+    (Action::PriorityNone, &["0"]),
+    (Action::Tag, &["#"]),
+    (Action::Remove, &["r"]),
+    (Action::Yank, &["y"]),
+    (Action::Cut, &["X"]),
+    (Action::MoveTo, &["m"]),
+    (Action::CopyTo, &["M"]),
+    (Action::Help, &["?", "F1"]),
+    (Action::Back, &["Esc"]),
+];
+
+/// Default keys of the pickers, the questions, the conflict prompt and the search input.
+/// Keys typed as text are left to the search input.
+pub const DIALOG_KEYS: &[(Action, &[&str])] = &[
+    (Action::Select, &["Enter"]),
+    (Action::Back, &["Esc"]),
+    (Action::Cancel, &["c", "n"]),
+    (Action::SaveAndQuit, &["s"]),
+    (Action::Discard, &["d"]),
+    (Action::Confirm, &["y"]),
+    (Action::KeepMine, &["k"]),
+    (Action::TakeTheirs, &["t"]),
+    (Action::Merge, &["m"]),
+    (Action::ToggleRegex, &["Ctrl+r"]),
+];
+
+/// Default keys of the calendar, which moves by days with `up`, `down`, `left` and `right` of
+/// normal mode and opens a day with `select` of the dialogs
+pub const CALENDAR_KEYS: &[(Action, &[&str])] = &[
+    (Action::PreviousMonth, &["[", "PageUp"]),
+    (Action::NextMonth, &["]", "PageDown"]),
+    (Action::Today, &["t"]),
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        ACTION_NAMES
+            .iter()
+            .find(|(action, _)| action == self)
+            .map_or("", |(_, name)| name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTION_NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(action, _)| *action)
+    }
+
+    /// What the action does, as shown by the help
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::CreateNote => "create a note",
+            Action::CreateFile => "create a file, folder/name creates it in a folder",
+            Action::CreateFolder => "create a folder",
+            Action::CreateList => "create a list",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Remove => "remove the current file, list or note",
+            Action::Edit => "rename the current file or list, edit the note",
+            Action::EditDescription => "edit the description of the current list",
+            Action::EditExternally => "edit the current file, list or note in $EDITOR",
+            Action::Save => "save",
+            Action::SaveAndQuit => "save and quit",
+            Action::Quit => "quit without saving",
+            Action::Help => "show this help",
+            Action::MoveUp => "move the current file, list or note up",
+            Action::MoveDown => "move the current file, list or note down",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "previous file or list",
+            Action::Right => "next file or list",
+            Action::Back => "back, clears the search",
+            Action::Visual => "visual mode to mark several notes",
+            Action::Yank => "yank the current list or note",
+            Action::Cut => "cut the current list or note",
+            Action::Paste => "paste after the current list or note",
+            Action::MoveTo => "move to another list or file",
+            Action::CopyTo => "copy to another list or file",
+            Action::Search => "search notes",
+            Action::NextMatch => "next match",
+            Action::PreviousMatch => "previous match",
+            Action::SearchResults => "list all matches",
+            Action::CycleState => "cycle the state of the note",
+            Action::Open => "set the state to open",
+            Action::InProgress => "set the state to in progress",
+            Action::Defer => "set the state to deferred",
+            Action::Block => "set the state to blocked",
+            Action::Done => "set the state to done",
+            Action::Reject => "set the state to rejected",
+            Action::Indent => "indent the note",
+            Action::Outdent => "outdent the note",
+            Action::Collapse => "collapse or expand subtasks",
+            Action::SortByPriority => "sort notes by priority",
+            Action::SortByDue => "sort notes by due date",
+            Action::Mark => "mark or unmark the note",
+            Action::MarkRange => "mark the range since the last marked note",
+            Action::MarkAll => "mark all notes",
+            Action::MarkOpen => "mark all open notes",
+            Action::MarkDone => "mark all done notes",
+            Action::MarkRejected => "mark all rejected notes",
+            Action::ClearMarks => "unmark all notes",
+            Action::PriorityHigh => "high priority",
+            Action::PriorityMedium => "medium priority",
+            Action::PriorityLow => "low priority",
+            Action::PriorityNone => "no priority",
+            Action::Tag => "add or remove tags",
+            Action::NextTheme => "switch theme",
+            Action::Journal => "open today's journal day or go back to the files",
+            Action::Calendar => "pick a journal day in the calendar",
+            Action::Select => "choose the selected entry",
+            Action::Cancel => "cancel",
+            Action::Discard => "discard the changes and quit",
+            Action::Confirm => "remove",
+            Action::KeepMine => "keep mine and overwrite the file on disk",
+            Action::TakeTheirs => "take theirs and drop my changes",
+            Action::Merge => "merge both",
+            Action::ToggleRegex => "switch between text and regex",
+            Action::PreviousMonth => "previous month",
+            Action::NextMonth => "next month",
+            Action::Today => "today",
+        }
+    }
+
+    /// Whether the action only does something while a note is selected
+    pub fn needs_note(&self) -> bool {
+        matches!(
+            self,
+            Action::Visual
+                | Action::CycleState
+                | Action::Open
+                | Action::InProgress
+                | Action::Defer
+                | Action::Block
+                | Action::Done
+                | Action::Reject
+                | Action::Indent
+                | Action::Outdent
+                | Action::Collapse
+        )
+    }
+}
+
+/// A key together with its modifiers. Shift is part of the char for letters and symbols,
+/// so `K` and `Shift+k` are the same binding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Parse a key such as `q`, `Ctrl+e`, `Shift+Up`, `Space` or `F1`
+    pub fn parse(key: &str) -> Result<KeyBinding, String> {
+        let invalid = || format!("Invalid key: \"{}\"", key);
+        // A trailing `+` is the key itself, as in `Ctrl++`
+        let (prefix, name) = match key.strip_suffix("++") {
+            Some(prefix) => (Some(prefix), "+"),
+            None => match key.rsplit_once('+') {
+                Some((prefix, name)) if !name.is_empty() => (Some(prefix), name),
+                _ => (None, key),
+            },
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.into_iter().flat_map(|prefix| prefix.split('+')) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(invalid()),
+                },
+            },
+        };
+        Ok(KeyBinding::new(code, modifiers))
+    }
+
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => KeyBinding {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::BackTab => KeyBinding {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => KeyBinding { code, modifiers },
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key: KeyEvent) -> Self {
+        KeyBinding::new(key.code, key.modifiers)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Action>,
+}
+
+impl Keymap {
+    /// Default bindings with some actions bound to other keys. Keys taken by a remapped
+    /// action are no longer bound to their default action.
+    pub fn new(
+        defaults: &[(Action, &[&str])],
+        overrides: &[(String, Vec<String>)],
+    ) -> Result<Keymap, Vec<String>> {
+        let mut errors = Vec::new();
+        let mut remapped: HashMap<KeyBinding, Action> = HashMap::new();
+        let mut overridden = Vec::new();
+        for (name, keys) in overrides {
+            let action = match Action::from_name(name) {
+                Some(action) if defaults.iter().any(|(a, _)| *a == action) => action,
+                _ => {
+                    errors.push(format!("Unknown action: \"{}\"", name));
+                    continue;
+                }
+            };
+            overridden.push(action);
+            for key in keys {
+                match KeyBinding::parse(key) {
+                    Ok(binding) => {
+                        if let Some(other) = remapped.insert(binding, action) {
+                            errors.push(format!(
+                                "\"{}\" is bound to both {} and {}",
+                                key,
+                                other.name(),
+                                action.name()
+                            ));
+                        }
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+
+        let mut bindings = HashMap::new();
+        for (action, keys) in defaults {
+            if overridden.contains(action) {
+                continue;
+            }
+            for key in keys.iter() {
+                let binding = KeyBinding::parse(key).expect("invalid default key");
+                bindings.insert(binding, *action);
+            }
+        }
+        bindings.retain(|binding, _| !remapped.contains_key(binding));
+        bindings.extend(remapped);
+
+        if errors.is_empty() {
+            Ok(Keymap { bindings })
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn get(&self, key: KeyEvent) -> Option<Action> {
+        self.bindings.get(&KeyBinding::from(key)).copied()
+    }
+
+    /// Keys bound to an action, special keys first
+    pub fn keys(&self, action: Action) -> Vec<KeyBinding> {
+        let mut keys: Vec<KeyBinding> = self
+            .bindings
+            .iter()
+            .filter(|(_, a)| **a == action)
+            .map(|(binding, _)| *binding)
+            .collect();
+        keys.sort_by_key(|binding| {
+            let is_char = matches!(binding.code, KeyCode::Char(c) if c != ' ');
+            (is_char, binding.to_string())
+        });
+        keys
+    }
+
+    /// Keys bound to any of the actions as shown in prompts, such as `Esc, c, n`
+    pub fn names(&self, actions: &[Action]) -> String {
+        actions
+            .iter()
+            .flat_map(|action| self.keys(*action))
+            .map(|key| key.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::keymap::{
+        Action, KeyBinding, Keymap, CALENDAR_KEYS, DIALOG_KEYS, NORMAL_KEYS, VISUAL_KEYS,
+    };
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn keymap_test() {
+        assert_eq!(
+            KeyBinding::parse("Shift+k"),
+            Ok(KeyBinding::new(KeyCode::Char('K'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            KeyBinding::parse("ctrl++").unwrap().modifiers,
+            KeyModifiers::CONTROL
+        );
+        assert_eq!(KeyBinding::parse("F5").unwrap().code, KeyCode::F(5));
+        assert!(KeyBinding::parse("Hyper+x").is_err());
+        assert!(KeyBinding::parse("F13").is_err());
+        for key in [
+            "Ctrl+e",
+            "Shift+Up",
+            "Space",
+            "BackTab",
+            "F1",
+            "K",
+            "Alt+Enter",
+        ] {
+            assert_eq!(KeyBinding::parse(key).unwrap().to_string(), key);
+        }
+
+        for (action, _) in NORMAL_KEYS
+            .iter()
+            .chain(VISUAL_KEYS)
+            .chain(DIALOG_KEYS)
+            .chain(CALENDAR_KEYS)
+        {
+            assert_eq!(Action::from_name(action.name()), Some(*action));
+        }
+        Keymap::new(VISUAL_KEYS, &[]).unwrap();
+        let dialog = Keymap::new(DIALOG_KEYS, &[]).unwrap();
+        assert_eq!(dialog.names(&[Action::Back, Action::Cancel]), "Esc, c, n");
+        Keymap::new(CALENDAR_KEYS, &[]).unwrap();
+
+        let overrides = vec![
+            ("done".to_string(), vec!["d".to_string()]),
+            ("right".to_string(), vec!["Right".to_string()]),
+        ];
+        let keymap = Keymap::new(NORMAL_KEYS, &overrides).unwrap();
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('d'), KeyModifiers::NONE)),
+            Some(Action::Done)
+        );
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('x'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('l'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('J'), KeyModifiers::SHIFT)),
+            Some(Action::MoveDown)
+        );
+        let keys: Vec<String> = keymap
+            .keys(Action::Up)
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        assert_eq!(keys, vec!["Up", "k", "w"]);
+
+        let overrides = vec![
+            ("undo".to_string(), vec!["u".to_string()]),
+            (
+                "redo".to_string(),
+                vec!["u".to_string(), "Super+u".to_string()],
+            ),
+            ("mark".to_string(), vec!["m".to_string()]),
+        ];
+        assert_eq!(Keymap::new(NORMAL_KEYS, &overrides).unwrap_err().len(), 3);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::ui::theme::Theme;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+
+fn code_style(theme: &Theme) -> Style {
+    Style::default().fg(theme.code)
+}
+
+fn link_style(theme: &Theme) -> Style {
+    Style::default()
+        .fg(theme.link)
+        .add_modifier(Modifier::UNDERLINED)
+}
+
+/// Lines of a description with basic Markdown styling. Headings are bold, code blocks and
+/// quotes are shown as they are, everything else supports inline styling.
+pub fn markdown_lines(text: &str, theme: &Theme) -> Vec<Spans<'static>> {
+    let mut in_fence = false;
+    text.split('\n')
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                Spans::from(Span::styled(line.to_string(), code_style(theme)))
+            } else if in_fence {
+                Spans::from(Span::styled(line.to_string(), code_style(theme)))
+            } else if let Some(title) = heading(line) {
+                Spans::from(Span::styled(
+                    title.to_string(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ))
+            } else if line.starts_with('>') {
+                Spans::from(Span::styled(
+                    line.to_string(),
+                    Style::default()
+                        .fg(theme.folder)
+                        .add_modifier(Modifier::ITALIC),
+                ))
+            } else {
+                Spans::from(markdown_spans(line, theme))
+            }
+        })
+        .collect()
+}
+
+/// Text of a `## heading` line
+fn heading(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    match level {
+        1..=6 => line[level..].strip_prefix(' ').map(str::trim),
+        _ => None,
+    }
+}
+
+enum Marker<'t> {
+    Code(&'t str),
+    Styled(&'t str, Modifier),
+    Link(&'t str),
+}
+
+/// A line with **bold**, *italic* or _italic_, `code` and [links](url) styled. Unclosed
+/// markers are shown as they are.
+pub fn markdown_spans(line: &str, theme: &Theme) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    inline(line, Style::default(), theme, &mut spans);
+    spans
+}
+
+fn inline(text: &str, style: Style, theme: &Theme, spans: &mut Vec<Span<'static>>) {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let previous = plain.chars().last();
+        let marker = match c {
+            '`' => rest[1..]
+                .find('`')
+                .map(|end| (end + 2, Marker::Code(&rest[1..end + 1]))),
+            '*' if rest.starts_with("**") => closing(&rest[2..], "**")
+                .map(|end| (end + 4, Marker::Styled(&rest[2..end + 2], Modifier::BOLD))),
+            '*' => closing(&rest[1..], "*")
+                .map(|end| (end + 2, Marker::Styled(&rest[1..end + 1], Modifier::ITALIC))),
+            // Underscores inside words, as in snake_case, are no markers
+            '_' if !previous.is_some_and(char::is_alphanumeric) => closing(&rest[1..], "_")
+                .map(|end| (end + 2, Marker::Styled(&rest[1..end + 1], Modifier::ITALIC))),
+            '[' => link(rest).map(|(length, label)| (length, Marker::Link(label))),
+            _ => None,
+        };
+        match marker {
+            Some((length, marker)) => {
+                flush(&mut plain, style, spans);
+                match marker {
+                    Marker::Code(code) => spans.push(Span::styled(
+                        code.to_string(),
+                        style.patch(code_style(theme)),
+                    )),
+                    Marker::Styled(inner, modifier) => {
+                        inline(inner, style.add_modifier(modifier), theme, spans)
+                    }
+                    Marker::Link(label) => {
+                        inline(label, style.patch(link_style(theme)), theme, spans)
+                    }
+                }
+                rest = &rest[length..];
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    flush(&mut plain, style, spans);
+}
+
+/// Offset of a closing marker, the styled text may neither be empty nor start with a space
+fn closing(text: &str, marker: &str) -> Option<usize> {
+    if text.is_empty() || text.starts_with(' ') {
+        return None;
+    }
+    text.find(marker).filter(|end| *end > 0)
+}
+
+/// Length of a `[label](url)` link at the start of the text and its label
+fn link(text: &str) -> Option<(usize, &str)> {
+    let label_end = text.find("](")?;
+    let url_end = text[label_end..].find(')')? + label_end;
+    Some((url_end + 1, &text[1..label_end]))
+}
+
+fn flush(plain: &mut String, style: Style, spans: &mut Vec<Span<'static>>) {
+    if !plain.is_empty() {
+        spans.push(Span::styled(std::mem::take(plain), style));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::markdown::{markdown_lines, markdown_spans};
+    use crate::ui::theme::Theme;
+    use tui::style::Modifier;
+
+    #[test]
+    fn markdown_test() {
+        let spans = markdown_spans(
+            "a **bold *both* text** and `co*de` [site](https://x.y) snake_case *",
+            &Theme::default(),
+        );
+        let parts: Vec<(&str, bool, bool)> = spans
+            .iter()
+            .map(|span| {
+                (
+                    span.content.as_ref(),
+                    span.style.add_modifier.contains(Modifier::BOLD),
+                    span.style.add_modifier.contains(Modifier::ITALIC),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("a ", false, false),
+                ("bold ", true, false),
+                ("both", true, true),
+                (" text", true, false),
+                (" and ", false, false),
+                ("co*de", false, false),
+                (" ", false, false),
+                ("site", false, false),
+                (" snake_case *", false, false),
+            ]
+        );
+        assert!(spans[7].style.add_modifier.contains(Modifier::UNDERLINED));
+
+        let lines = markdown_lines("## Sub\n```\n*raw*\n```\ntext", &Theme::default());
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0].0[0].content, "Sub");
+        assert_eq!(lines[2].0[0].content, "*raw*");
+    }
+}
@@ -0,0 +1,27 @@
+use crate::todo::{FileList, Note};
+
+/// Notes or lists which were yanked or cut, ready to be pasted
+#[derive(Clone, Debug)]
+pub enum Register {
+    /// Top level notes with their subtasks
+    Notes(Vec<Note>),
+    Lists(FileList),
+}
+
+impl Register {
+    pub fn describe(&self) -> String {
+        let (count, name) = match self {
+            Register::Notes(notes) => (notes.len(), "note"),
+            Register::Lists(lists) => (lists.titles.len(), "list"),
+        };
+        format!("{} {}{}", count, name, if count == 1 { "" } else { "s" })
+    }
+}
+
+/// File or list offered by the send to picker
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Destination {
+    pub file_index: usize,
+    /// Lists are sent to files, notes to a list
+    pub list_index: Option<usize>,
+}
@@ -0,0 +1,95 @@
+use crate::todo::{filter_notes, note_at, FileList, Note};
+use regex::{Regex, RegexBuilder};
+
+/// Query entered in search mode, matched case-insensitively against note content
+pub struct Search {
+    pub query: String,
+    pub regex: bool,
+    compiled: Option<Regex>,
+}
+
+/// Location of a note matching the search
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchMatch {
+    pub file_index: usize,
+    pub list_index: usize,
+    pub path: Vec<usize>,
+}
+
+impl Search {
+    pub fn new(query: &str, regex: bool) -> Self {
+        let compiled = if regex {
+            RegexBuilder::new(query).case_insensitive(true).build().ok()
+        } else {
+            None
+        };
+        Search {
+            query: query.to_string(),
+            regex,
+            compiled,
+        }
+    }
+
+    /// Whether the query is a valid regular expression, plain queries are always valid
+    pub fn is_valid(&self) -> bool {
+        !self.regex || self.compiled.is_some()
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        if self.regex {
+            self.compiled
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(text))
+        } else {
+            text.to_lowercase().contains(&self.query.to_lowercase())
+        }
+    }
+
+    /// Every matching note of every file, in display order
+    pub fn find(&self, lists: &[FileList]) -> Vec<SearchMatch> {
+        let mut matches = Vec::new();
+        for (file_index, list) in lists.iter().enumerate() {
+            for (list_index, notes) in list.notes.iter().enumerate() {
+                let filter = |note: &Note| self.matches(&note.content);
+                for path in filter_notes(notes, &filter) {
+                    if filter(note_at(notes, &path).unwrap()) {
+                        matches.push(SearchMatch {
+                            file_index,
+                            list_index,
+                            path,
+                        });
+                    }
+                }
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::parse_lines;
+    use crate::ui::search::{Search, SearchMatch};
+
+    #[test]
+    fn find_test() {
+        let content = "# One\n- [ ] Buy milk\n- [ ] parent\n  - [x] milk again\n# Two\n- [ ] bread";
+        let lists = vec![parse_lines(content.split('\n').map(String::from).collect())];
+
+        let matches = Search::new("MILK", false).find(&lists);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(
+            matches[1],
+            SearchMatch {
+                file_index: 0,
+                list_index: 0,
+                path: vec![1, 0]
+            }
+        );
+
+        let search = Search::new("^b(read|uy)", true);
+        assert!(search.is_valid());
+        assert_eq!(search.find(&lists).len(), 2);
+        assert!(!Search::new("(", true).is_valid());
+    }
+}
@@ -0,0 +1,102 @@
+/// Notes marked in visual mode, only valid for the list they were marked in
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    pub file_index: usize,
+    pub list_index: usize,
+    pub marked: Vec<Vec<usize>>,
+    /// Index of the visible note a range was started at
+    pub anchor: Option<usize>,
+}
+
+impl Selection {
+    pub fn new(file_index: usize, list_index: usize) -> Self {
+        Selection {
+            file_index,
+            list_index,
+            ..Default::default()
+        }
+    }
+
+    pub fn toggle(&mut self, path: &[usize]) {
+        match self.marked.iter().position(|marked| marked == path) {
+            Some(index) => {
+                self.marked.remove(index);
+            }
+            None => self.marked.push(path.to_vec()),
+        }
+    }
+
+    /// Marked notes together with the range between the anchor and the cursor, in display
+    /// order
+    pub fn paths(&self, visible: &[Vec<usize>], cursor: usize) -> Vec<Vec<usize>> {
+        let range = match self.anchor {
+            Some(anchor) => anchor.min(cursor)..anchor.max(cursor) + 1,
+            None => 0..0,
+        };
+        visible
+            .iter()
+            .enumerate()
+            .filter(|(i, path)| range.contains(i) || self.marked.contains(path))
+            .map(|(_, path)| path.clone())
+            .collect()
+    }
+
+    /// Turn the current range into marks
+    pub fn close_range(&mut self, visible: &[Vec<usize>], cursor: usize) {
+        self.marked = self.paths(visible, cursor);
+        self.anchor = None;
+    }
+
+    /// Keep the marks on their notes when a note is inserted at `path`
+    pub fn insert(&mut self, path: &[usize]) {
+        let depth = path.len() - 1;
+        for marked in self.marked.iter_mut() {
+            if marked.len() > depth
+                && marked[..depth] == path[..depth]
+                && marked[depth] >= path[depth]
+            {
+                marked[depth] += 1;
+            }
+        }
+    }
+}
+
+/// Paths without the ones contained in another path, subtasks go along with their parent
+pub fn top_level(paths: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    paths
+        .iter()
+        .filter(|path| {
+            !paths
+                .iter()
+                .any(|other| other.len() < path.len() && path.starts_with(other))
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::selection::{top_level, Selection};
+
+    #[test]
+    fn selection_test() {
+        let visible = vec![vec![0], vec![0, 0], vec![1], vec![2]];
+        let mut selection = Selection::new(0, 0);
+        selection.toggle(&[2]);
+        selection.anchor = Some(1);
+        assert_eq!(
+            selection.paths(&visible, 0),
+            vec![vec![0], vec![0, 0], vec![2]]
+        );
+        selection.close_range(&visible, 0);
+        selection.toggle(&[0]);
+        assert_eq!(selection.paths(&visible, 3), vec![vec![0, 0], vec![2]]);
+        selection.insert(&[1]);
+        selection.insert(&[0, 0]);
+        assert_eq!(selection.marked, vec![vec![0, 1], vec![3]]);
+        assert_eq!(
+            top_level(&[vec![0], vec![0, 0], vec![0, 1, 2], vec![1]]),
+            vec![vec![0], vec![1]]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use tui::style::Color;
+
+/// Names of the built-in themes, in the order they are switched through
+pub const THEMES: &[&str] = &["dark", "light", "high-contrast", "terminal"];
+
+/// Colors of the app
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Built-in theme the colors are based on
+    pub name: &'static str,
+    pub background: Color,
+    pub foreground: Color,
+    /// Borders of the file and list tabs
+    pub accent: Color,
+    /// Background of the selected file, list or note
+    pub highlight: Color,
+    /// First letter of tab names
+    pub tab_first: Color,
+    pub tab_rest: Color,
+    /// Muted text such as the folder of a file or quotes
+    pub folder: Color,
+    pub marked: Color,
+    pub message: Color,
+    pub input: Color,
+    pub overdue: Color,
+    pub due: Color,
+    pub priority_high: Color,
+    pub priority: Color,
+    pub tag: Color,
+    pub context: Color,
+    pub code: Color,
+    pub link: Color,
+    /// Checkboxes of notes by their state
+    pub in_progress: Color,
+    pub deferred: Color,
+    pub blocked: Color,
+    pub done: Color,
+    pub rejected: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            name: "dark",
+            background: Color::Rgb(31, 41, 55),
+            foreground: Color::White,
+            accent: Color::Cyan,
+            highlight: Color::DarkGray,
+            tab_first: Color::Yellow,
+            tab_rest: Color::Green,
+            folder: Color::Gray,
+            marked: Color::LightYellow,
+            message: Color::LightRed,
+            input: Color::LightCyan,
+            overdue: Color::Red,
+            due: Color::Yellow,
+            priority_high: Color::Red,
+            priority: Color::Magenta,
+            tag: Color::Cyan,
+            context: Color::Green,
+            code: Color::Yellow,
+            link: Color::LightBlue,
+            in_progress: Color::LightBlue,
+            deferred: Color::LightMagenta,
+            blocked: Color::LightRed,
+            done: Color::LightGreen,
+            rejected: Color::Gray,
+        }
+    }
+
+    /// Dark text on a light background
+    pub fn light() -> Theme {
+        Theme {
+            name: "light",
+            background: Color::Rgb(249, 250, 251),
+            foreground: Color::Rgb(17, 24, 39),
+            accent: Color::Rgb(29, 78, 216),
+            highlight: Color::Rgb(209, 213, 219),
+            tab_first: Color::Rgb(180, 83, 9),
+            tab_rest: Color::Rgb(21, 128, 61),
+            folder: Color::Rgb(107, 114, 128),
+            marked: Color::Rgb(161, 98, 7),
+            message: Color::Rgb(185, 28, 28),
+            input: Color::Rgb(29, 78, 216),
+            overdue: Color::Rgb(185, 28, 28),
+            due: Color::Rgb(180, 83, 9),
+            priority_high: Color::Rgb(185, 28, 28),
+            priority: Color::Rgb(126, 34, 206),
+            tag: Color::Rgb(3, 105, 161),
+            context: Color::Rgb(21, 128, 61),
+            code: Color::Rgb(180, 83, 9),
+            link: Color::Rgb(29, 78, 216),
+            in_progress: Color::Rgb(29, 78, 216),
+            deferred: Color::Rgb(126, 34, 206),
+            blocked: Color::Rgb(185, 28, 28),
+            done: Color::Rgb(21, 128, 61),
+            rejected: Color::Rgb(107, 114, 128),
+        }
+    }
+
+    /// Bright colors on black
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: "high-contrast",
+            background: Color::Black,
+            foreground: Color::White,
+            accent: Color::LightCyan,
+            highlight: Color::Blue,
+            tab_first: Color::LightYellow,
+            tab_rest: Color::White,
+            folder: Color::Gray,
+            marked: Color::LightYellow,
+            message: Color::LightRed,
+            input: Color::LightCyan,
+            overdue: Color::LightRed,
+            due: Color::LightYellow,
+            priority_high: Color::LightRed,
+            priority: Color::LightMagenta,
+            tag: Color::LightCyan,
+            context: Color::LightGreen,
+            code: Color::LightYellow,
+            link: Color::LightBlue,
+            in_progress: Color::LightBlue,
+            deferred: Color::LightMagenta,
+            blocked: Color::LightRed,
+            done: Color::LightGreen,
+            rejected: Color::Gray,
+        }
+    }
+
+    /// The terminal's own background and foreground with its palette of named colors
+    pub fn terminal() -> Theme {
+        Theme {
+            name: "terminal",
+            background: Color::Reset,
+            foreground: Color::Reset,
+            accent: Color::Cyan,
+            highlight: Color::DarkGray,
+            tab_first: Color::Yellow,
+            tab_rest: Color::Green,
+            folder: Color::DarkGray,
+            marked: Color::Yellow,
+            message: Color::Red,
+            input: Color::Cyan,
+            overdue: Color::Red,
+            due: Color::Yellow,
+            priority_high: Color::Red,
+            priority: Color::Magenta,
+            tag: Color::Cyan,
+            context: Color::Green,
+            code: Color::Yellow,
+            link: Color::Blue,
+            in_progress: Color::Blue,
+            deferred: Color::Magenta,
+            blocked: Color::Red,
+            done: Color::Green,
+            rejected: Color::DarkGray,
+        }
+    }
+
+    /// Built-in theme by its name
+    pub fn preset(name: &str) -> Result<Theme, String> {
+        match name {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "high-contrast" => Ok(Theme::high_contrast()),
+            "terminal" => Ok(Theme::terminal()),
+            _ => Err(format!(
+                "Unknown theme: \"{}\", expected one of {}",
+                name,
+                THEMES.join(", ")
+            )),
+        }
+    }
+
+    /// Built-in theme following this one
+    pub fn next_name(&self) -> &'static str {
+        let index = THEMES.iter().position(|name| *name == self.name);
+        THEMES[index.map_or(0, |i| (i + 1) % THEMES.len())]
+    }
+
+    /// Change a color by its name as used in the config file
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let color = match name {
+            "background" => &mut self.background,
+            "foreground" => &mut self.foreground,
+            "accent" => &mut self.accent,
+            "highlight" => &mut self.highlight,
+            "tab_first" => &mut self.tab_first,
+            "tab_rest" => &mut self.tab_rest,
+            "folder" => &mut self.folder,
+            "marked" => &mut self.marked,
+            "message" => &mut self.message,
+            "input" => &mut self.input,
+            "overdue" => &mut self.overdue,
+            "due" => &mut self.due,
+            "priority_high" => &mut self.priority_high,
+            "priority" => &mut self.priority,
+            "tag" => &mut self.tag,
+            "context" => &mut self.context,
+            "code" => &mut self.code,
+            "link" => &mut self.link,
+            "in_progress" => &mut self.in_progress,
+            "deferred" => &mut self.deferred,
+            "blocked" => &mut self.blocked,
+            "done" => &mut self.done,
+            "rejected" => &mut self.rejected,
+            _ => return Err(format!("Unknown color: \"{}\"", name)),
+        };
+        *color = parse_color(value)?;
+        Ok(())
+    }
+}
+
+/// Parse a color name such as `lightcyan`, `reset` for the terminal's default or `#1f2937`
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    let invalid = || format!("Invalid color: \"{}\"", value);
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        return Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    let color = match value.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return Err(invalid()),
+    };
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::theme::{parse_color, Theme, THEMES};
+    use tui::style::Color;
+
+    #[test]
+    fn theme_test() {
+        assert_eq!(parse_color("#1F2937"), Ok(Color::Rgb(31, 41, 55)));
+        assert_eq!(parse_color("Light-Cyan"), Ok(Color::LightCyan));
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("purple").is_err());
+
+        let mut theme = Theme::default();
+        theme.set("background", "reset").unwrap();
+        assert_eq!(theme.background, Color::Reset);
+        assert!(theme.set("border", "red").is_err());
+
+        let mut name = Theme::default().name;
+        for _ in THEMES {
+            name = Theme::preset(name).unwrap().next_name();
+        }
+        assert_eq!(name, "dark");
+        assert!(Theme::preset("solarized").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use crate::todo::{parse_lines, FileList};
+use std::fs::{metadata, read_to_string};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often the data directory is checked for changes made by other programs
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Modification time and size, a file whose stamp changed is read again
+pub type Stamp = Option<(SystemTime, u64)>;
+
+/// Last version of a file known to be on disk
+#[derive(Clone, Debug)]
+pub struct DiskFile {
+    pub stamp: Stamp,
+    /// Common ancestor for merging changes made on both sides
+    pub base: FileList,
+}
+
+/// A file changed both on disk and in yoku
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    pub path: PathBuf,
+    pub stamp: Stamp,
+    pub theirs: FileList,
+    /// Notes changed on both sides, found when merging
+    pub notes: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Resolution {
+    KeepMine,
+    TakeTheirs,
+    Merge,
+}
+
+pub fn stamp(path: &Path) -> Stamp {
+    let metadata = metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Parse a file, `None` if it cannot be read (anymore)
+pub fn read_file(path: &Path) -> Option<FileList> {
+    let content = read_to_string(path).ok()?;
+    Some(parse_lines(content.split('\n').map(String::from).collect()))
+}
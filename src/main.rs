@@ -1,3 +1,4 @@
+use crossterm::cursor::Show;
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use crossterm::{
@@ -5,24 +6,26 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
 use dirs::{data_dir, home_dir};
+use lib::cli;
+use lib::config::{config_path, load_config};
+use lib::meta::SortKey;
 use lib::todo::{
-    extract_naked_filename, lines_from_file, parse_lines, FileList, MAIN_DIR, STARTER_FILE,
-    STARTER_FILE_CONTENT,
+    apply_order, file_name, lines_from_file, parse_lines, todo_files, FileList, MAIN_DIR,
+    STARTER_FILE, STARTER_FILE_CONTENT,
 };
 use lib::ui::app::App;
 use lib::ui::run_app;
 use lib::util::calculate_hash;
-use regex::Regex;
 use std::collections::HashMap;
 use std::process::exit;
+use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
 use std::{
     error::Error,
-    fs::read_dir,
     fs::{create_dir_all, File},
     io,
-    io::Write,
+    io::{Read, Write},
     path::{Path, PathBuf},
 };
 use structopt::StructOpt;
@@ -42,12 +45,148 @@ struct Opt {
 
     #[structopt(short = "d", long = "data-path", help = "Check the default data path")]
     check_path: bool,
+
+    #[structopt(
+        short = "c",
+        long = "config",
+        help = "Config file to use instead of the default one",
+        parse(from_os_str)
+    )]
+    config: Option<PathBuf>,
+
+    #[structopt(
+        short = "t",
+        long = "theme",
+        help = "Colors to use: dark, light, high-contrast or terminal"
+    )]
+    theme: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    #[structopt(about = "Add a note to a list, creating the list if needed")]
+    Add {
+        #[structopt(help = "<file>/<list> or <file> for its first list")]
+        target: String,
+        content: String,
+    },
+    #[structopt(about = "Show files and lists, or the notes of a file or list")]
+    Ls {
+        #[structopt(help = "<file> or <file>/<list>")]
+        target: Option<String>,
+        #[structopt(
+            short = "t",
+            long = "tag",
+            help = "Only show notes with a #tag or @context"
+        )]
+        tag: Option<String>,
+        #[structopt(
+            short = "s",
+            long = "sort",
+            help = "Sort notes by due date or priority",
+            possible_values = &["due", "priority"]
+        )]
+        sort: Option<SortKey>,
+    },
+    #[structopt(about = "Create a file, optionally from a template")]
+    New {
+        #[structopt(help = "Name of the file, folder/name creates it in a folder")]
+        name: String,
+        #[structopt(
+            short = "t",
+            long = "template",
+            help = "Template of the templates folder next to the config file"
+        )]
+        template: Option<String>,
+    },
+    #[structopt(about = "Mark a note as done")]
+    Done {
+        #[structopt(help = "<file>/<list>")]
+        target: String,
+        #[structopt(help = "Note number as shown by ls or part of its content")]
+        note: String,
+    },
+    #[structopt(about = "Show notes completed this week or since a date")]
+    Completed {
+        #[structopt(help = "<file> or <file>/<list>, all files by default")]
+        target: Option<String>,
+        #[structopt(
+            short = "s",
+            long = "since",
+            help = "First day as YYYY-MM-DD, defaults to the Monday of this week"
+        )]
+        since: Option<String>,
+    },
+    #[structopt(about = "Remove a note, a list or a file")]
+    Rm {
+        #[structopt(help = "<file> or <file>/<list>")]
+        target: String,
+        #[structopt(help = "Note number as shown by ls or part of its content")]
+        note: Option<String>,
+    },
+    #[structopt(about = "Move a note to another list")]
+    Mv {
+        #[structopt(help = "<file>/<list>")]
+        target: String,
+        #[structopt(help = "Note number as shown by ls or part of its content")]
+        note: String,
+        #[structopt(help = "Destination <file>/<list>")]
+        destination: String,
+    },
+    #[structopt(about = "Print all files of the data directory")]
+    Export {
+        #[structopt(long = "format", default_value = "json", possible_values = &["json"])]
+        format: Format,
+    },
+    #[structopt(about = "Write files to the data directory, replacing files of the same name")]
+    Import {
+        #[structopt(long = "format", default_value = "json", possible_values = &["json"])]
+        format: Format,
+        #[structopt(help = "File to read, defaults to stdin")]
+        input: Option<PathBuf>,
+    },
+    #[structopt(about = "Open today's journal day, creating it if needed")]
+    Today,
+}
+
+#[derive(Debug)]
+enum Format {
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unsupported format: {s}")),
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let _re = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
     let opt = Opt::from_args();
 
+    let config = match load_config(opt.config.as_deref()) {
+        Ok(mut config) => {
+            if let Some(theme) = &opt.theme {
+                if let Err(err) = config.set_theme(theme) {
+                    eprintln!("{err}");
+                    exit(1);
+                }
+            }
+            config
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            exit(1);
+        }
+    };
+
     let main_path = if !opt.main_path.is_empty() {
         PathBuf::from(opt.main_path)
     } else {
@@ -68,6 +207,12 @@ fn main() -> Result<(), Box<dyn Error>> {
             "Current default data path is \"{}\"",
             main_path.to_str().unwrap()
         );
+        if let Some(path) = config_path() {
+            println!("Default config path is \"{}\"", path.display());
+        }
+        if let Some(dir) = &config.templates_dir {
+            println!("Templates are read from \"{}\"", dir.display());
+        }
         exit(0);
     }
 
@@ -86,10 +231,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         sleep(Duration::from_secs(3));
     }
 
-    let mut path_entries = read_dir(&main_path)?
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, io::Error>>()?;
-    path_entries.sort();
+    let mut path_entries = todo_files(&main_path);
+    apply_order(&main_path, &mut path_entries);
 
     if path_entries.is_empty() {
         let mut starter_path = main_path.clone();
@@ -101,42 +244,122 @@ fn main() -> Result<(), Box<dyn Error>> {
             .unwrap_or_else(|_| panic!("Could not write to file {starter_path:?}"));
 
         // Redefine path_entries
-        path_entries = read_dir(&main_path)?
-            .map(|res| res.map(|e| e.path()))
-            .collect::<Result<Vec<_>, io::Error>>()?;
-        path_entries.sort();
+        path_entries = todo_files(&main_path);
     }
 
     let mut lists: Vec<FileList> = Vec::new();
     let mut files: Vec<String> = Vec::new();
     let mut paths: Vec<PathBuf> = Vec::new();
-    let mut hashes: HashMap<&PathBuf, u64> = HashMap::new();
+    let mut hashes: HashMap<PathBuf, u64> = HashMap::new();
     let mut to_remove: Vec<PathBuf> = Vec::new();
+    // Files which cannot be read are left out and left alone
+    let mut unreadable: Vec<String> = Vec::new();
 
     // Parse all paths
     for path in path_entries {
-        let lines = lines_from_file(&path);
+        let lines = match lines_from_file(&path) {
+            Ok(lines) => lines,
+            Err(err) => {
+                unreadable.push(err);
+                continue;
+            }
+        };
         let filelist = parse_lines(lines);
-        files.push(extract_naked_filename(&path));
+        files.push(file_name(&main_path, &path));
 
         paths.push(path);
         lists.push(filelist);
     }
 
     // Create initial hashes (for checking whether a list needs to be written to file)
-    let tmp_paths = paths.clone();
-    for (i, list) in lists.iter().enumerate() {
-        hashes.insert(tmp_paths.get(i).unwrap(), calculate_hash(list));
+    for (path, list) in paths.iter().zip(lists.iter()) {
+        hashes.insert(path.clone(), calculate_hash(list));
+    }
+
+    let journal = matches!(opt.command, Some(Command::Today));
+    if let Some(command) = opt.command.filter(|_| !journal) {
+        for err in unreadable.iter() {
+            eprintln!("{err}");
+        }
+        let mut app = App::new(
+            &mut files,
+            &mut paths,
+            &mut lists,
+            &mut hashes,
+            &main_path,
+            &mut to_remove,
+        );
+        app.config = config;
+        let res = match &command {
+            Command::Add { target, content } => cli::add(&mut app, target, content),
+            Command::Ls { target, tag, sort } => {
+                cli::list(&mut app, target.as_deref(), tag.as_deref(), *sort)
+            }
+            Command::New { name, template } => cli::new_file(&mut app, name, template.as_deref()),
+            Command::Done { target, note } => cli::done(&mut app, target, note),
+            Command::Completed { target, since } => {
+                cli::completed(&app, target.as_deref(), since.as_deref())
+            }
+            Command::Rm { target, note } => cli::remove(&mut app, target, note.as_deref()),
+            Command::Mv {
+                target,
+                note,
+                destination,
+            } => cli::move_note(&mut app, target, note, destination),
+            Command::Export {
+                format: Format::Json,
+            } => cli::export_json(&app),
+            Command::Import {
+                format: Format::Json,
+                input,
+            } => {
+                let mut content = String::new();
+                match input {
+                    Some(path) => File::open(path)?.read_to_string(&mut content)?,
+                    None => io::stdin().read_to_string(&mut content)?,
+                };
+                cli::import_json(&mut app, &content)
+            }
+            Command::Today => unreachable!(),
+        };
+        match res {
+            Ok(output) => {
+                if !output.is_empty() {
+                    println!("{output}");
+                }
+                if let Err(err) = app.save() {
+                    eprintln!("{err}");
+                    exit(1);
+                }
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                exit(1);
+            }
+        }
+        return Ok(());
     }
 
     // TERMINAL
+    // Leave raw mode and the alternate screen before a panic message is printed
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            Show
+        );
+        default_hook(info);
+    }));
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = App::new(
+    let mut app = App::new(
         &mut files,
         &mut paths,
         &mut lists,
@@ -144,6 +367,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         &main_path,
         &mut to_remove,
     );
+    app.config = config;
+    if !unreadable.is_empty() {
+        app.message = Some(unreadable.join(", "));
+    }
+    if journal {
+        app.open_today();
+    }
     let res = run_app(&mut terminal, app);
 
     // restore terminal